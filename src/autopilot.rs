@@ -0,0 +1,310 @@
+use crate::camera::{Camera, look_rotation};
+use crate::celestial::ship::Ship;
+use crate::orbit_camera::{self, DEFAULT_DISTANCE};
+use crate::scene::{Body, NodeId, Scene};
+use crate::warp::WarpEffect;
+use nalgebra_glm::Vec3;
+
+// Seconds the trip takes at cruise speed, roughly, whatever the distance
+const CRUISE_TIME: f32 = 4.0;
+const MIN_CRUISE_SPEED: f32 = 2.0;
+// Seconds to reach cruise speed, and to brake from it
+const ACCELERATION_TIME: f32 = 1.5;
+// Fraction of the remaining turn done per second
+const TURN_RATE: f32 = 4.0;
+// Heading error, in radians, below which the engines start
+const ALIGNED: f32 = 0.08;
+// How wide a berth other bodies get, in their radii
+const CLEARANCE: f32 = 3.0;
+// Bodies in a row the route is bent around before giving up on the rest
+const MAX_DETOURS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Phase {
+    // Turning towards the target before setting off
+    Align,
+    Accelerate,
+    // At full speed, with the warp engaged
+    Cruise,
+    Decelerate,
+    // Parked at a fixed distance, carried along with the target and facing
+    // it. Not an orbit: a planet's Hill sphere ends well short of that
+    // distance, so nothing could circle it there under the scene's gravity
+    Hold,
+}
+
+impl Phase {
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Align => "aligning",
+            Phase::Accelerate => "accelerating",
+            Phase::Cruise => "cruising at warp",
+            Phase::Decelerate => "decelerating",
+            Phase::Hold => "holding station",
+        }
+    }
+}
+
+// Flies the ship to a body: turns towards it, accelerates, cruises under
+// warp, brakes and holds station next to it until the pilot takes over. The
+// route is replanned every frame, so it follows a moving target and steers
+// around whatever stars and planets are in the way.
+//
+// It flies the ship kinematically: it sets the ship's position, velocity and
+// orientation itself instead of firing the engines and RCS, so the ship's
+// thrust, turn rate and the pull of gravity don't limit it. Ship::update and
+// Ship::steer don't run while it's engaged; the velocity it leaves behind
+// lets the ship carry on under its own physics once the pilot takes over.
+pub struct Autopilot {
+    pub target: NodeId,
+    pub phase: Phase,
+    speed: f32,
+    cruise_speed: f32,
+    acceleration: f32,
+    // Where the target was last frame
+    last_goal: Vec3,
+}
+
+impl Autopilot {
+    pub fn new(scene: &Scene, target: NodeId, ship: &Ship) -> Self {
+        let goal = scene.nodes[target].world_position;
+        let cruise_speed = ((goal - ship.position).magnitude() / CRUISE_TIME).max(MIN_CRUISE_SPEED);
+        Autopilot {
+            target,
+            phase: Phase::Align,
+            speed: 0.0,
+            cruise_speed,
+            acceleration: cruise_speed / ACCELERATION_TIME,
+            last_goal: goal,
+        }
+    }
+
+    // Moves the ship along the route for `delta_time` real seconds, while the
+    // bodies moved for `elapsed` simulated ones. Returns true once parked next
+    // to the target.
+    pub fn update(&mut self, scene: &Scene, ship: &mut Ship, warp: &mut WarpEffect, delta_time: f32, elapsed: f32) -> bool {
+        let goal = scene.nodes[self.target].world_position;
+        let motion = goal - self.last_goal;
+        self.last_goal = goal;
+        ship.angular_velocity = Vec3::zeros();
+
+        if self.phase == Phase::Hold {
+            ship.position += motion;
+            // Leave with the target's velocity, so letting go doesn't fling
+            // the ship off
+            if elapsed > 0.0 {
+                ship.velocity = motion / elapsed;
+            }
+            turn_towards(ship, goal - ship.position, delta_time);
+            return false;
+        }
+        // Once under way, speeds are relative to the target, so however fast
+        // it moves it can't run away
+        if self.phase != Phase::Align {
+            ship.position += motion;
+        }
+
+        let park_distance = scene.nodes[self.target].body.radius() * DEFAULT_DISTANCE;
+        let mut away = ship.position - goal;
+        if away.magnitude() < 1e-6 {
+            away = -ship.forward();
+        }
+        let arrival = parking_spot(scene, self.target, goal, away.normalize(), park_distance);
+
+        let waypoint = detour(scene, self.target, ship.position, arrival);
+        let heading = waypoint.unwrap_or(arrival);
+        let remaining = (heading - ship.position).magnitude() + waypoint.map_or(0.0, |w| (arrival - w).magnitude());
+
+        let to_heading = heading - ship.position;
+        turn_towards(ship, to_heading, delta_time);
+        // Already on the heading counts as facing it
+        let error = to_heading.try_normalize(1e-6).map_or(0.0, |direction| ship.forward().angle(&direction));
+
+        let braking_distance = self.speed * self.speed / (2.0 * self.acceleration);
+        self.phase = match self.phase {
+            Phase::Align if error < ALIGNED => Phase::Accelerate,
+            Phase::Align => Phase::Align,
+            _ if remaining <= braking_distance => Phase::Decelerate,
+            Phase::Decelerate => Phase::Decelerate,
+            _ if self.speed >= self.cruise_speed => Phase::Cruise,
+            phase => phase,
+        };
+        self.speed = match self.phase {
+            Phase::Align | Phase::Hold => 0.0,
+            Phase::Accelerate | Phase::Cruise => (self.speed + self.acceleration * delta_time).min(self.cruise_speed),
+            // Ease off so the stop lands right on the parking point
+            Phase::Decelerate => (2.0 * self.acceleration * remaining).sqrt().max(self.cruise_speed * 0.01),
+        };
+        warp.set_active(self.phase == Phase::Cruise);
+
+        let step = (self.speed * delta_time).min(to_heading.magnitude());
+        let direction = to_heading.try_normalize(1e-6).unwrap_or(Vec3::zeros());
+        ship.position += direction * step;
+        ship.velocity = direction * self.speed;
+        if elapsed > 0.0 && self.phase != Phase::Align {
+            ship.velocity += motion / elapsed;
+        }
+
+        if self.phase == Phase::Decelerate && remaining - step < park_distance * 0.01 {
+            self.phase = Phase::Hold;
+            self.speed = 0.0;
+            return true;
+        }
+        false
+    }
+}
+
+// Turns the ship towards `direction`, gradually so the view doesn't snap
+fn turn_towards(ship: &mut Ship, direction: Vec3, delta_time: f32) {
+    if direction.magnitude() < 1e-6 {
+        return;
+    }
+    let mut desired = look_rotation(&direction, &ship.up());
+    if ship.orientation.dot(&desired) < 0.0 {
+        desired = -desired;
+    }
+    let t = (TURN_RATE * delta_time).min(1.0);
+    ship.orientation = nalgebra_glm::quat_slerp(&ship.orientation, &desired, t);
+}
+
+// Where to park, `distance` from the target along `away`, unless that's too
+// close to another body, like the planet a moon circles; then on the target's
+// far side from it
+fn parking_spot(scene: &Scene, target: NodeId, goal: Vec3, away: Vec3, distance: f32) -> Vec3 {
+    let spot = goal + away * distance;
+    match bodies(scene, target).find(|(center, radius)| (spot - center).magnitude() < radius * CLEARANCE) {
+        Some((center, _)) => goal + (goal - center).try_normalize(1e-6).unwrap_or(away) * distance,
+        None => spot,
+    }
+}
+
+// Centre and radius of every body the ship could crash into, but the target
+fn bodies(scene: &Scene, target: NodeId) -> impl Iterator<Item = (Vec3, f32)> + '_ {
+    scene
+        .nodes
+        .iter()
+        .enumerate()
+        .filter(move |&(id, _)| id != target)
+        .filter_map(|(_, node)| {
+            let radius = match &node.body {
+                Body::Star(star) => star.radius,
+                Body::Planet(planet) => planet.radius,
+                Body::BlackHole(hole) => hole.disk_outer,
+                _ => return None,
+            };
+            Some((node.world_position, radius))
+        })
+}
+
+// A point to steer through when a body other than the target sits on the
+// straight line to `arrival`. The first body in the way is passed on the side
+// the line leans towards; the waypoint is pushed clear of any body it lands
+// in, and the leg to it is checked the same way, so the route only bends
+// towards points that are themselves reachable.
+fn detour(scene: &Scene, target: NodeId, from: Vec3, arrival: Vec3) -> Option<Vec3> {
+    // A moon parks within the berth of its planet; keep the berth short of
+    // the parking spot so it stays reachable
+    let obstacles: Vec<(Vec3, f32)> = bodies(scene, target)
+        .map(|(center, radius)| (center, (radius * CLEARANCE).min((arrival - center).magnitude() * 0.9)))
+        .collect();
+
+    let mut waypoint = None;
+    let mut goal = arrival;
+    for _ in 0..MAX_DETOURS {
+        let Some(point) = first_blocker(&obstacles, from, goal) else {
+            break;
+        };
+        let point = push_clear(&obstacles, point);
+        waypoint = Some(point);
+        goal = point;
+    }
+    waypoint
+}
+
+// Where to pass the first obstacle on the straight line from `from` to `to`
+fn first_blocker(obstacles: &[(Vec3, f32)], from: Vec3, to: Vec3) -> Option<Vec3> {
+    let path = to - from;
+    let length = path.magnitude();
+    if length < 1e-6 {
+        return None;
+    }
+    let direction = path / length;
+
+    obstacles
+        .iter()
+        .filter_map(|&(center, clearance)| {
+            let along = (center - from).dot(&direction).clamp(0.0, length);
+            let offset = from + direction * along - center;
+            if offset.magnitude() >= clearance {
+                return None;
+            }
+            // Pass on the side the line already leans towards
+            let side = if offset.magnitude() > 1e-6 {
+                offset.normalize()
+            } else {
+                direction.cross(&Vec3::y()).try_normalize(1e-6).unwrap_or(Vec3::x())
+            };
+            Some((along, center + side * clearance * 1.2))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, waypoint)| waypoint)
+}
+
+// Moves a point out of every obstacle's clearance it falls in, a few passes
+// over in case moving out of one lands it in the next
+fn push_clear(obstacles: &[(Vec3, f32)], mut point: Vec3) -> Vec3 {
+    for _ in 0..MAX_DETOURS {
+        let Some(&(center, clearance)) = obstacles.iter().find(|(center, clearance)| (point - center).magnitude() < *clearance) else {
+            break;
+        };
+        let outward = (point - center).try_normalize(1e-6).unwrap_or(Vec3::y());
+        point = center + outward * clearance * 1.2;
+    }
+    point
+}
+
+// The star or planet closest to the middle of the view
+pub fn target_in_view(scene: &Scene, camera: &Camera) -> Option<NodeId> {
+    orbit_camera::targets(scene)
+        .into_iter()
+        .filter(|&id| (scene.nodes[id].world_position - camera.eye).magnitude() > 1e-6)
+        .map(|id| (id, camera.forward().angle(&(scene.nodes[id].world_position - camera.eye))))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(id, _)| id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::Mesh;
+    use crate::scene_file;
+
+    #[test]
+    fn flies_to_a_moon_and_parks_next_to_it() {
+        let mut scene = scene_file::load(&format!("{}/scenes/default.toml", env!("CARGO_MANIFEST_DIR"))).unwrap().scene;
+        let (luna, terra) = (scene.find("Luna").unwrap(), scene.find("Terra").unwrap());
+        // Start across the system, with the Sun in the way
+        let mut ship = Ship::from_mesh(Mesh::new(Vec::new()));
+        ship.position = -scene.nodes[luna].world_position.normalize() * 30.0;
+
+        let mut pilot = Autopilot::new(&scene, luna, &ship);
+        let mut warp = WarpEffect::new();
+        let frame = 1.0 / 60.0;
+        // A minute of flight at normal speed
+        for _ in 0..3600 {
+            scene.update(frame as f64);
+            let parked = pilot.update(&scene, &mut ship, &mut warp, frame, frame);
+            for (center, radius) in bodies(&scene, luna) {
+                assert!((ship.position - center).magnitude() > radius, "flew into a body at {:?}", ship.position);
+            }
+            if parked {
+                break;
+            }
+        }
+
+        assert_eq!(pilot.phase, Phase::Hold);
+        let distance = (ship.position - scene.nodes[luna].world_position).magnitude();
+        assert!((distance - 0.18 * DEFAULT_DISTANCE).abs() < 0.05, "parked {} away", distance);
+        assert!((ship.position - scene.nodes[terra].world_position).magnitude() > 0.7);
+    }
+}
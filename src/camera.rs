@@ -1,54 +1,97 @@
-use nalgebra_glm::{Vec3, Mat4, look_at};
+use nalgebra_glm::{Vec3, Mat3, Mat4, Quat, look_at};
 
+// Camera space axes: right is +X, up is +Y and the camera looks down -Z
+const LOCAL_RIGHT: Vec3 = Vec3::new(1.0, 0.0, 0.0);
+const LOCAL_UP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+const LOCAL_FORWARD: Vec3 = Vec3::new(0.0, 0.0, -1.0);
+
+// Free-flight camera. The orientation is a quaternion so it can yaw, pitch
+// and roll any amount without gimbal lock; every rotation is around the
+// camera's own axes, like a spacecraft.
 pub struct Camera {
     pub eye: Vec3,
-    pub center: Vec3,
-    pub up: Vec3,
+    pub orientation: Quat,
 }
 
 impl Camera {
     pub fn new(eye: Vec3, center: Vec3, up: Vec3) -> Self {
-        Camera { eye, center, up }
+        Camera {
+            eye,
+            orientation: look_rotation(&(center - eye), &up),
+        }
     }
 
     pub fn get_view_matrix(&self) -> Mat4 {
-        look_at(&self.eye, &self.center, &self.up)
+        look_at(&self.eye, &(self.eye + self.forward()), &self.up())
     }
 
     pub fn forward(&self) -> Vec3 {
-        (self.center - self.eye).normalize()
+        nalgebra_glm::quat_rotate_vec3(&self.orientation, &LOCAL_FORWARD)
     }
 
     pub fn right(&self) -> Vec3 {
-        self.forward().cross(&self.up).normalize()
+        nalgebra_glm::quat_rotate_vec3(&self.orientation, &LOCAL_RIGHT)
+    }
+
+    pub fn up(&self) -> Vec3 {
+        nalgebra_glm::quat_rotate_vec3(&self.orientation, &LOCAL_UP)
+    }
+
+    // Moves to `eye` and turns towards `target` with no roll
+    pub fn look_at(&mut self, eye: Vec3, target: Vec3) {
+        self.eye = eye;
+        if (target - eye).magnitude() > 1e-6 {
+            self.orientation = look_rotation(&(target - eye), &LOCAL_UP);
+        }
+    }
+
+    // A point straight ahead, for saving the view as an eye and a target
+    pub fn target(&self) -> Vec3 {
+        self.eye + self.forward()
     }
 
     pub fn move_forward(&mut self, distance: f32) {
-        let direction = self.forward();
-        self.eye += direction * distance;
-        self.center += direction * distance;
+        self.eye += self.forward() * distance;
     }
 
     pub fn move_right(&mut self, distance: f32) {
-        let direction = self.right();
-        self.eye += direction * distance;
-        self.center += direction * distance;
+        self.eye += self.right() * distance;
     }
 
     pub fn move_up(&mut self, distance: f32) {
-        self.eye.y += distance;
-        self.center.y += distance;
+        self.eye += self.up() * distance;
     }
 
-    pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
-        let radius = (self.eye - self.center).magnitude();
-
-        let current_direction = (self.eye - self.center).normalize();
+    // Turns left/right, up/down and around the view direction, in radians
+    pub fn rotate(&mut self, yaw: f32, pitch: f32, roll: f32) {
+        let turn = nalgebra_glm::quat_angle_axis(yaw, &LOCAL_UP)
+            * nalgebra_glm::quat_angle_axis(pitch, &LOCAL_RIGHT)
+            * nalgebra_glm::quat_angle_axis(roll, &LOCAL_FORWARD);
+        // Renormalize so rounding errors don't build up into a skewed view
+        self.orientation = nalgebra_glm::quat_normalize(&(self.orientation * turn));
+    }
 
-        let right = current_direction.cross(&self.up).normalize();
-        let rotated_yaw = nalgebra_glm::rotate_vec3(&current_direction, delta_yaw, &self.up);
-        let rotated = nalgebra_glm::rotate_vec3(&rotated_yaw, delta_pitch, &right);
+    // Swings the camera around a pivot, turning it so the pivot stays in the same
+    // place on screen
+    pub fn orbit(&mut self, pivot: &Vec3, delta_yaw: f32, delta_pitch: f32) {
+        let swing = nalgebra_glm::quat_angle_axis(delta_yaw, &self.up())
+            * nalgebra_glm::quat_angle_axis(delta_pitch, &self.right());
 
-        self.eye = self.center + rotated * radius;
+        self.eye = pivot + nalgebra_glm::quat_rotate_vec3(&swing, &(self.eye - pivot));
+        self.orientation = nalgebra_glm::quat_normalize(&(swing * self.orientation));
     }
 }
+
+// Orientation that looks along `direction` with `up` as close to straight up as possible
+pub fn look_rotation(direction: &Vec3, up: &Vec3) -> Quat {
+    let forward = direction.normalize();
+    // Looking straight along `up` leaves the roll undefined; pick any other axis
+    let up = if forward.cross(up).magnitude() < 1e-6 {
+        if forward.x.abs() < 0.9 { LOCAL_RIGHT } else { LOCAL_UP }
+    } else {
+        *up
+    };
+    let right = forward.cross(&up).normalize();
+    let up = right.cross(&forward);
+    nalgebra_glm::mat3_to_quat(&Mat3::from_columns(&[right, up, -forward]))
+}
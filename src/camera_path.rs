@@ -0,0 +1,344 @@
+use nalgebra_glm::Vec3;
+use serde::Deserialize;
+
+const MIN_FOV: f32 = 1.0 * std::f32::consts::PI / 180.0;
+const MAX_FOV: f32 = 170.0 * std::f32::consts::PI / 180.0;
+
+// A scripted camera flight read from a TOML file: keyframes with a position,
+// a point to look at and a field of view, smoothed into one continuous move.
+// Times are in seconds from the start of the path.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PathFile {
+    #[serde(default)]
+    interpolation: Interpolation,
+    #[serde(default)]
+    easing: Easing,
+    // Frames per second when rendering the path to images
+    #[serde(default = "default_fps")]
+    fps: f32,
+    // Simulated seconds per second of path while rendering to images
+    #[serde(default = "default_time_scale")]
+    time_scale: f64,
+    #[serde(rename = "keyframe", default)]
+    keyframes: Vec<KeyframeEntry>,
+}
+
+fn default_fps() -> f32 {
+    30.0
+}
+
+fn default_time_scale() -> f64 {
+    1.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyframeEntry {
+    time: f32,
+    position: [f32; 3],
+    target: [f32; 3],
+    // Vertical field of view in degrees
+    #[serde(default = "default_fov")]
+    fov: f32,
+}
+
+fn default_fov() -> f32 {
+    45.0
+}
+
+#[derive(Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+    // Passes through every keyframe at its time
+    #[default]
+    CatmullRom,
+    // Keyframes are control points of a single curve that starts at the first
+    // and ends at the last, only drawn towards the ones in between
+    Bezier,
+}
+
+// Catmull-Rom paths ease within their first and last segments only, so every
+// keyframe keeps its time and the speed stays continuous where they join the
+// rest. A Bézier curve has no times between its ends and is eased as a whole.
+#[derive(Clone, Copy, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    #[default]
+    EaseInOut,
+}
+
+impl Easing {
+    fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+
+    // Remaps progress through one Catmull-Rom segment: from rest at the start
+    // of the path and to rest at its end, otherwise unchanged
+    fn segment(self, s: f32, first: bool, last: bool) -> f32 {
+        let start = if first && matches!(self, Easing::EaseIn | Easing::EaseInOut) { 0.0 } else { 1.0 };
+        let end = if last && matches!(self, Easing::EaseOut | Easing::EaseInOut) { 0.0 } else { 1.0 };
+        // Hermite curve from 0 to 1 with those slopes at either end
+        let (s2, s3) = (s * s, s * s * s);
+        start * (s3 - 2.0 * s2 + s) + (-2.0 * s3 + 3.0 * s2) + end * (s3 - s2)
+    }
+}
+
+// Where the camera is at one moment of the path
+#[derive(Clone, Copy)]
+pub struct CameraPose {
+    pub position: Vec3,
+    pub target: Vec3,
+    // Vertical field of view in radians
+    pub fov: f32,
+}
+
+struct Keyframe {
+    time: f32,
+    position: Vec3,
+    target: Vec3,
+    fov: f32,
+}
+
+pub struct CameraPath {
+    pub interpolation: Interpolation,
+    pub easing: Easing,
+    pub fps: f32,
+    pub time_scale: f64,
+    keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+        let file: PathFile = toml::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+        Self::build(file).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    fn build(file: PathFile) -> Result<Self, String> {
+        if file.keyframes.len() < 2 {
+            return Err("a camera path needs at least two keyframes".to_string());
+        }
+        if file.fps <= 0.0 {
+            return Err("fps must be positive".to_string());
+        }
+        if file.time_scale <= 0.0 {
+            return Err("time_scale must be positive".to_string());
+        }
+
+        let mut keyframes = Vec::new();
+        for (index, entry) in file.keyframes.iter().enumerate() {
+            let fail = |message: &str| format!("keyframe #{}: {}", index + 1, message);
+            if let Some(previous) = keyframes.last().map(|k: &Keyframe| k.time) {
+                if entry.time <= previous {
+                    return Err(fail("times must increase from one keyframe to the next"));
+                }
+            } else if entry.time < 0.0 {
+                return Err(fail("time cannot be negative"));
+            }
+            if !(entry.fov > 0.0 && entry.fov < 180.0) {
+                return Err(fail("fov must be between 0 and 180 degrees"));
+            }
+            if entry.position == entry.target {
+                return Err(fail("position and target cannot be the same point"));
+            }
+
+            let [x, y, z] = entry.position;
+            let [tx, ty, tz] = entry.target;
+            keyframes.push(Keyframe {
+                time: entry.time,
+                position: Vec3::new(x, y, z),
+                target: Vec3::new(tx, ty, tz),
+                fov: entry.fov.to_radians(),
+            });
+        }
+
+        Ok(CameraPath {
+            interpolation: file.interpolation,
+            easing: file.easing,
+            fps: file.fps,
+            time_scale: file.time_scale,
+            keyframes,
+        })
+    }
+
+    // Time of the last keyframe
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |k| k.time)
+    }
+
+    pub fn sample(&self, time: f32) -> CameraPose {
+        let first = self.keyframes[0].time;
+        let time = time.clamp(first, self.duration());
+
+        match self.interpolation {
+            Interpolation::CatmullRom => self.catmull_rom(time),
+            Interpolation::Bezier => self.bezier(self.easing.apply((time - first) / (self.duration() - first))),
+        }
+    }
+
+    // Cubic Hermite segments with Catmull-Rom tangents; with keyframes spaced
+    // unevenly in time the tangents are taken per second, so the speed stays
+    // continuous across keyframes
+    fn catmull_rom(&self, time: f32) -> CameraPose {
+        let keys = &self.keyframes;
+        let last = keys.len() - 1;
+        let i = keys.iter().rposition(|k| k.time <= time).unwrap_or(0).min(last - 1);
+        let (a, b) = (&keys[i], &keys[i + 1]);
+        let span = b.time - a.time;
+        let s = self.easing.segment((time - a.time) / span, i == 0, i + 1 == last);
+
+        let tangent = |j: usize, value: &dyn Fn(&Keyframe) -> Vec3| {
+            let (before, after) = (j.saturating_sub(1), (j + 1).min(last));
+            (value(&keys[after]) - value(&keys[before])) / (keys[after].time - keys[before].time)
+        };
+        let hermite = |value: &dyn Fn(&Keyframe) -> Vec3| {
+            let (s2, s3) = (s * s, s * s * s);
+            value(a) * (2.0 * s3 - 3.0 * s2 + 1.0)
+                + tangent(i, value) * (span * (s3 - 2.0 * s2 + s))
+                + value(b) * (-2.0 * s3 + 3.0 * s2)
+                + tangent(i + 1, value) * (span * (s3 - s2))
+        };
+
+        CameraPose {
+            position: hermite(&|k| k.position),
+            target: hermite(&|k| k.target),
+            // Tangents can overshoot between keyframes; keep the lens sensible
+            fov: hermite(&|k| Vec3::new(k.fov, 0.0, 0.0)).x.clamp(MIN_FOV, MAX_FOV),
+        }
+    }
+
+    // De Casteljau's algorithm over all the keyframes
+    fn bezier(&self, t: f32) -> CameraPose {
+        let mut points: Vec<CameraPose> = self
+            .keyframes
+            .iter()
+            .map(|k| CameraPose { position: k.position, target: k.target, fov: k.fov })
+            .collect();
+
+        while points.len() > 1 {
+            points = points
+                .windows(2)
+                .map(|pair| CameraPose {
+                    position: pair[0].position.lerp(&pair[1].position, t),
+                    target: pair[0].target.lerp(&pair[1].target, t),
+                    fov: pair[0].fov + (pair[1].fov - pair[0].fov) * t,
+                })
+                .collect();
+        }
+        points[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYS: &str = "
+[[keyframe]]
+time = 0.0
+position = [0.0, 0.0, 10.0]
+target = [0.0, 0.0, 0.0]
+
+[[keyframe]]
+time = 2.0
+position = [10.0, 2.0, 0.0]
+target = [0.0, 0.0, 0.0]
+fov = 60.0
+
+[[keyframe]]
+time = 3.0
+position = [0.0, 4.0, -10.0]
+target = [1.0, 0.0, 0.0]
+
+[[keyframe]]
+time = 6.0
+position = [-10.0, 0.0, 0.0]
+target = [0.0, 1.0, 0.0]
+fov = 30.0
+";
+
+    fn build(header: &str, keys: &str) -> Result<CameraPath, String> {
+        CameraPath::build(toml::from_str(&format!("{}\n{}", header, keys)).map_err(|e| e.to_string())?)
+    }
+
+    fn error(header: &str, keys: &str) -> String {
+        build(header, keys).err().unwrap_or_default()
+    }
+
+    fn close(a: &CameraPose, b: &CameraPose) -> bool {
+        (a.position - b.position).magnitude() < 1e-4 && (a.target - b.target).magnitude() < 1e-4 && (a.fov - b.fov).abs() < 1e-5
+    }
+
+    fn key(path: &CameraPath, index: usize) -> CameraPose {
+        let k = &path.keyframes[index];
+        CameraPose { position: k.position, target: k.target, fov: k.fov }
+    }
+
+    #[test]
+    fn starts_and_ends_on_the_first_and_last_keyframes() {
+        for interpolation in ["catmull_rom", "bezier"] {
+            for easing in ["linear", "ease_in", "ease_out", "ease_in_out"] {
+                let header = format!("interpolation = \"{}\"\neasing = \"{}\"", interpolation, easing);
+                let path = build(&header, KEYS).unwrap();
+                assert!(close(&path.sample(0.0), &key(&path, 0)), "{} {}", interpolation, easing);
+                assert!(close(&path.sample(path.duration()), &key(&path, 3)), "{} {}", interpolation, easing);
+                // Past either end it holds still
+                assert!(close(&path.sample(-1.0), &key(&path, 0)));
+                assert!(close(&path.sample(10.0), &key(&path, 3)));
+            }
+        }
+    }
+
+    #[test]
+    fn catmull_rom_passes_every_keyframe_at_its_time() {
+        let path = build("", KEYS).unwrap();
+        for index in 0..path.keyframes.len() {
+            assert!(close(&path.sample(path.keyframes[index].time), &key(&path, index)), "keyframe {}", index);
+        }
+    }
+
+    #[test]
+    fn eases_only_the_ends_of_a_catmull_rom_path() {
+        let eased = build("easing = \"ease_in_out\"", KEYS).unwrap();
+        let linear = build("easing = \"linear\"", KEYS).unwrap();
+        let speed = |path: &CameraPath, time: f32| (path.sample(time + 1e-3).position - path.sample(time).position).magnitude() / 1e-3;
+
+        // Starting and stopping at rest
+        assert!(speed(&eased, 0.0) < 0.05 * speed(&linear, 0.0));
+        assert!(speed(&eased, 6.0 - 1e-3) < 0.05 * speed(&linear, 6.0 - 1e-3));
+        // and untouched in between
+        for time in [2.0, 2.3, 2.7, 3.0] {
+            assert!(close(&eased.sample(time), &linear.sample(time)), "t = {}", time);
+        }
+    }
+
+    #[test]
+    fn rejects_bad_keyframes() {
+        let one = "[[keyframe]]\ntime = 0.0\nposition = [0.0, 0.0, 1.0]\ntarget = [0.0, 0.0, 0.0]\n";
+        assert!(error("", one).contains("at least two keyframes"));
+
+        let backwards = KEYS.replace("time = 3.0", "time = 1.0");
+        assert!(error("", &backwards).contains("times must increase"));
+
+        let negative = KEYS.replace("time = 0.0", "time = -1.0");
+        assert!(error("", &negative).contains("time cannot be negative"));
+
+        let wide = KEYS.replace("fov = 60.0", "fov = 180.0");
+        assert!(error("", &wide).contains("fov must be between"));
+
+        let blind = KEYS.replace("position = [-10.0, 0.0, 0.0]", "position = [0.0, 1.0, 0.0]");
+        assert!(error("", &blind).contains("cannot be the same point"));
+
+        assert!(error("fps = 0.0", KEYS).contains("fps must be positive"));
+        assert!(error("time_scale = -1.0", KEYS).contains("time_scale must be positive"));
+    }
+}
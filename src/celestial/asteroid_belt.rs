@@ -0,0 +1,101 @@
+use crate::celestial::OrbitalElements;
+use crate::geometry::{Mesh, create_rock};
+use nalgebra_glm::{Mat4, Vec3};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+// Distinct rock meshes shared by the whole belt
+const SHAPES: u32 = 6;
+
+pub struct Asteroid {
+    pub orbit: OrbitalElements,
+    pub size: f32,
+    pub tumble_axis: Vec3,
+    // Seconds per turn about the tumble axis, negative for the other way round
+    pub tumble_period: f32,
+    pub shape: usize,
+}
+
+// Thousands of small rocks between two orbit radii. Nothing is stepped: every
+// rock's place and orientation follow from the scene time when it is drawn.
+pub struct AsteroidBelt {
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+    // Upper bound for each rock's inclination, in degrees
+    pub max_inclination: f64,
+    pub seed: u32,
+    // Seconds the rocks had already orbited at scene time zero, so a saved
+    // belt picks up where it was instead of starting over
+    pub elapsed: f64,
+    pub asteroids: Vec<Asteroid>,
+    pub shapes: Vec<Mesh>,
+    // Model matrices as of the last update, one list per shape. Refilling
+    // them costs a Kepler solve per rock on every simulation step; the lists
+    // keep their capacity so no step allocates.
+    pub transforms: Vec<Vec<Mat4>>,
+}
+
+impl AsteroidBelt {
+    // `central_mass` is the gravitational parameter of the body the belt circles
+    pub fn new(count: usize, inner_radius: f64, outer_radius: f64, max_inclination: f64, seed: u32, central_mass: f64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed as u64);
+
+        let shapes = (0..SHAPES).map(|i| create_rock(seed ^ (i * 7919), 8)).collect();
+
+        let asteroids = (0..count)
+            .map(|_| {
+                let orbit = OrbitalElements::new(
+                    rng.gen_range(inner_radius..outer_radius),
+                    rng.gen_range(0.0..0.15),
+                    rng.gen_range(0.0..max_inclination.max(f64::EPSILON)),
+                    rng.gen_range(0.0..360.0),
+                    rng.gen_range(0.0..360.0),
+                    rng.gen_range(0.0..360.0),
+                )
+                .around(central_mass);
+
+                let axis = Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+                let direction = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+
+                Asteroid {
+                    orbit,
+                    // Mostly pebbles, with the odd large boulder
+                    size: 0.03 + 0.13 * rng.gen::<f32>().powi(4),
+                    tumble_axis: if axis.norm() > 1e-3 { axis.normalize() } else { Vec3::y() },
+                    tumble_period: rng.gen_range(5.0..40.0) * direction,
+                    shape: rng.gen_range(0..SHAPES as usize),
+                }
+            })
+            .collect();
+
+        AsteroidBelt {
+            inner_radius,
+            outer_radius,
+            max_inclination,
+            seed,
+            elapsed: 0.0,
+            asteroids,
+            transforms: vec![Vec::new(); SHAPES as usize],
+            shapes,
+        }
+    }
+
+    // Places every rock at `time` within `frame`, grouped by shape so each
+    // group is drawn from one mesh
+    pub fn update_transforms(&mut self, frame: &Mat4, time: f64) {
+        for group in &mut self.transforms {
+            group.clear();
+        }
+
+        let time = time + self.elapsed;
+        for asteroid in &self.asteroids {
+            let position = asteroid.orbit.position_at(time);
+            let turns = (time / asteroid.tumble_period as f64).rem_euclid(1.0) as f32;
+
+            let transform = nalgebra_glm::translate(frame, &position);
+            let transform = nalgebra_glm::rotate(&transform, turns * std::f32::consts::TAU, &asteroid.tumble_axis);
+            let transform = nalgebra_glm::scale(&transform, &Vec3::new(asteroid.size, asteroid.size, asteroid.size));
+            self.transforms[asteroid.shape].push(transform);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use crate::celestial::spin::Spin;
+use crate::geometry::{Mesh, create_ring};
+use crate::shaders::disk_shader::DiskFlow;
+use nalgebra_glm::Vec3;
+
+// Light speed in scene units per second. Low enough that a black hole ten
+// times the default Sun's mass has a horizon half a unit across, and that its
+// disk moves at a sizeable fraction of it.
+pub const SPEED_OF_LIGHT: f64 = 40.0;
+
+// Innermost stable circular orbit, in Schwarzschild radii
+const DISK_INNER: f32 = 3.0;
+const DISK_OUTER: f32 = 12.0;
+
+// A black hole and its accretion disk. The hole itself is only a shadow: it is
+// drawn by the lensing pass, which bends whatever lies behind it.
+pub struct BlackHole {
+    pub position: Vec3,
+    // Gravitational parameter, like a star's
+    pub mass: f64,
+    // Tilts the disk; the spin period is normally zero, the gas flow is animated by the shader
+    pub spin: Spin,
+    pub disk_inner: f32,
+    pub disk_outer: f32,
+    // At the inner edge, falling off outwards
+    pub disk_temperature: f32,
+    pub seed: u32,
+    pub disk: Mesh,
+}
+
+impl BlackHole {
+    pub fn new(mass: f64) -> Self {
+        let horizon = schwarzschild_radius(mass);
+        let mut hole = BlackHole {
+            position: Vec3::zeros(),
+            mass,
+            spin: Spin::new(0.0, 0.0),
+            disk_inner: horizon * DISK_INNER,
+            disk_outer: horizon * DISK_OUTER,
+            disk_temperature: 7000.0,
+            seed: 0,
+            disk: Mesh::new(Vec::new()),
+        };
+        hole.set_disk(hole.disk_inner, hole.disk_outer);
+        hole
+    }
+
+    pub fn set_disk(&mut self, inner_radius: f32, outer_radius: f32) {
+        self.disk_inner = inner_radius;
+        self.disk_outer = outer_radius;
+        self.disk = create_ring(inner_radius, outer_radius, 128);
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.spin.update(delta_time);
+    }
+
+    pub fn schwarzschild_radius(&self) -> f32 {
+        schwarzschild_radius(self.mass)
+    }
+
+    pub fn flow(&self) -> DiskFlow {
+        DiskFlow {
+            inner_radius: self.disk_inner,
+            outer_radius: self.disk_outer,
+            temperature: self.disk_temperature,
+            gm: self.mass as f32,
+            light_speed: SPEED_OF_LIGHT as f32,
+        }
+    }
+}
+
+pub fn schwarzschild_radius(mass: f64) -> f32 {
+    (2.0 * mass / (SPEED_OF_LIGHT * SPEED_OF_LIGHT)) as f32
+}
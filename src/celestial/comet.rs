@@ -0,0 +1,249 @@
+use crate::celestial::{OrbitalElements, Spin};
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::geometry::{Mesh, create_rock};
+use crate::particles::{Blend, Particle, ParticleSystem, glow};
+use crate::uniforms::Uniforms;
+use nalgebra_glm::{DVec3, Vec3};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+// Particles alive in each tail at full activity
+const ION_PARTICLES: f32 = 500.0;
+const DUST_PARTICLES: f32 = 700.0;
+
+const ION_COLOR: Color = Color::new(90, 150, 255);
+const DUST_COLOR: Color = Color::new(255, 225, 170);
+const COMA_COLOR: Color = Color::new(170, 255, 200);
+
+#[derive(Clone, Copy)]
+enum Tail {
+    // Gas ionised by sunlight and blown straight out by the solar wind
+    Ion,
+    // Grains pushed gently by radiation pressure, so they lag along the orbit
+    Dust,
+}
+
+// Icy nucleus on an eccentric orbit. Its coma and tails only exist inside
+// `active_distance` from the star that lights it, and grow as it closes in.
+pub struct Comet {
+    pub position: Vec3,
+    pub radius: f32,
+    pub spin: Spin,
+    pub seed: u32,
+    pub active_distance: f64,
+    // 0 while frozen, approaching 1 close to the star
+    pub activity: f32,
+    pub mesh: Mesh,
+    pub ion_tail: ParticleSystem,
+    pub dust_tail: ParticleSystem,
+    // Particle sizes and speeds, set by the perihelion distance and speed
+    scale: f32,
+    speed: f32,
+    // Where the star lighting the tails is, relative to the body the comet orbits
+    sun: Vec3,
+    // Scene time of the last tail update
+    updated_at: Option<f64>,
+}
+
+impl Comet {
+    pub fn new(radius: f32, seed: u32, active_distance: f64) -> Self {
+        let mut mesh = create_rock(seed, 10);
+        for vertex in &mut mesh.vertices {
+            vertex.position *= radius;
+        }
+
+        Comet {
+            position: Vec3::zeros(),
+            radius,
+            spin: Spin::new(20.0, 30.0),
+            seed,
+            active_distance,
+            activity: 0.0,
+            mesh,
+            ion_tail: ParticleSystem::new(Blend::Additive),
+            dust_tail: ParticleSystem::new(Blend::Additive),
+            scale: radius,
+            speed: 0.0,
+            sun: Vec3::zeros(),
+            updated_at: None,
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        self.spin.update(delta_time);
+    }
+
+    // Steps the tails by `delta_time` and refills them with particles born in
+    // that span. Everything is measured against the perihelion passage, so the
+    // tails look alike for any orbit size and survive heavy time warp: when
+    // the step outlasts a particle's life the tail is simply rebuilt. `sun` is
+    // where the star lighting the comet is, relative to the body it orbits;
+    // it is taken as fixed over a tail's life.
+    pub fn update_tails(&mut self, orbit: Option<&OrbitalElements>, sun: Option<Vec3>, time: f64, delta_time: f64) {
+        // After a jump in time (a new date, a loaded scene) the old tails are meaningless
+        let continuous = self
+            .updated_at
+            .is_some_and(|last| (time - delta_time - last).abs() <= 1e-9 * time.abs().max(1.0));
+        self.updated_at = Some(time);
+
+        let (Some(orbit), Some(sun)) = (orbit, sun) else {
+            self.activity = 0.0;
+            self.ion_tail.clear();
+            self.dust_tail.clear();
+            return;
+        };
+
+        let gm = orbit.mean_motion.powi(2) * orbit.semi_major_axis.powi(3);
+        let perihelion = orbit.semi_major_axis * (1.0 - orbit.eccentricity);
+        let perihelion_speed = (gm * (1.0 + orbit.eccentricity) / perihelion).sqrt();
+        // How long the nucleus takes to cover its perihelion distance
+        let passage = (perihelion / perihelion_speed) as f32;
+
+        self.scale = perihelion as f32;
+        self.speed = perihelion_speed as f32;
+        self.sun = sun;
+        self.activity = self.activity_at((orbit.position_at(time) - sun).norm() as f64);
+        self.dust_tail.attractor = Some((Vec3::zeros(), gm as f32));
+        self.dust_tail.max_step = passage * 0.02;
+
+        if continuous {
+            self.ion_tail.update(delta_time as f32);
+            self.dust_tail.update(delta_time as f32);
+        } else {
+            self.ion_tail.clear();
+            self.dust_tail.clear();
+        }
+
+        for (tail, lifetime) in [(Tail::Ion, passage * 0.6), (Tail::Dust, passage * 2.5)] {
+            // Running forwards the tail is missing its youngest particles;
+            // running backwards, its oldest ones
+            let span = if continuous { (delta_time.abs() as f32).min(lifetime) } else { lifetime };
+            let oldest = if delta_time >= 0.0 { span } else { lifetime };
+            self.emit(tail, orbit, time, oldest - span, oldest, lifetime);
+        }
+    }
+
+    fn activity_at(&self, distance: f64) -> f32 {
+        // Sublimation climbs steeply with sunlight, then saturates
+        let heat = ((self.active_distance / distance.max(1e-6)).powi(2) - 1.0).max(0.0);
+        (heat / (1.0 + heat)) as f32
+    }
+
+    // Adds the particles that would have been emitted between `oldest` and
+    // `youngest` seconds ago
+    fn emit(&mut self, tail: Tail, orbit: &OrbitalElements, time: f64, youngest: f32, oldest: f32, lifetime: f32) {
+        let speed = self.speed;
+        // Seeded from the moment, so a rendered fly-by comes out the same every time
+        let stream = u64::from(matches!(tail, Tail::Dust));
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed as u64 ^ time.to_bits() ^ youngest.to_bits() as u64 ^ stream);
+        let span = oldest - youngest;
+
+        let budget = match tail {
+            Tail::Ion => ION_PARTICLES,
+            Tail::Dust => DUST_PARTICLES,
+        };
+        let expected = budget * span / lifetime;
+        let count = expected as usize + usize::from(rng.gen::<f32>() < expected.fract());
+
+        for _ in 0..count {
+            let age = rng.gen_range(youngest..=oldest);
+            let (position, velocity) = orbit.state_at(time - age as f64);
+            let position = to_vec3(&position);
+            let velocity = to_vec3(&velocity);
+            let from_sun = position - self.sun;
+            let distance = from_sun.norm().max(1e-6);
+            let activity = self.activity_at(distance as f64);
+            if activity <= 0.0 {
+                continue;
+            }
+
+            let away = from_sun / distance;
+            let jitter = Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+
+            let particle = match tail {
+                Tail::Ion => {
+                    let velocity = away * speed * rng.gen_range(3.0..5.0) + jitter * speed * 0.1;
+                    Particle::new(position, velocity, lifetime, ION_COLOR * (0.35 * activity), self.scale * 0.04)
+                }
+                Tail::Dust => {
+                    let velocity = velocity + away * speed * rng.gen_range(0.05..0.3) + jitter * speed * 0.05;
+                    let mut particle =
+                        Particle::new(position, velocity, lifetime, DUST_COLOR * (0.25 * activity), self.scale * 0.07);
+                    // Radiation pressure cancels part of the star's pull, more for smaller grains
+                    particle.gravity = 1.0 - rng.gen_range(0.1..0.6);
+                    particle
+                }
+            };
+
+            match tail {
+                Tail::Ion => self.ion_tail.emit(particle, age),
+                Tail::Dust => self.dust_tail.emit(particle, age),
+            }
+        }
+    }
+
+    // Tails and coma, after the nucleus has been drawn; `origin` is the world
+    // position of the body the comet orbits
+    pub fn render_tails(&self, origin: &Vec3, framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
+        self.dust_tail.render(origin, framebuffer, uniforms);
+        self.ion_tail.render(origin, framebuffer, uniforms);
+
+        if self.activity <= 0.0 {
+            return;
+        }
+        let Some(center) = uniforms.world_to_screen(&self.position) else {
+            return;
+        };
+        let coma = self.radius + self.scale * 0.15 * self.activity;
+        let Some(edge) = uniforms.world_to_screen(&(self.position + uniforms.camera_right() * coma)) else {
+            return;
+        };
+        if center.z < -1.0 || center.z > 1.0 {
+            return;
+        }
+
+        let radius = (edge.xy() - center.xy()).norm().clamp(1.5, 60.0);
+
+        // The gas envelops the nucleus, so test depth from the coma's near side
+        let view = &uniforms.view_matrix;
+        let toward_camera = Vec3::new(view[(2, 0)], view[(2, 1)], view[(2, 2)]);
+        let depth = uniforms
+            .world_to_screen(&(self.position + toward_camera * coma))
+            .map_or(center.z, |near| near.z);
+        let center = Vec3::new(center.x, center.y, depth);
+
+        glow(framebuffer, &center, radius, &(COMA_COLOR * (0.8 * self.activity)));
+        glow(framebuffer, &center, radius * 0.3, &(Color::new(255, 255, 255) * self.activity));
+    }
+}
+
+fn to_vec3(v: &DVec3) -> Vec3 {
+    Vec3::new(v.x as f32, v.y as f32, v.z as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ion_tail_points_away_from_the_star_not_the_parent() {
+        // At perihelion, halfway between the barycenter it orbits and a star
+        let orbit = OrbitalElements::new(10.0, 0.6, 0.0, 0.0, 0.0, 0.0).around(40.96);
+        let mut comet = Comet::new(0.1, 3, 20.0);
+        comet.update_tails(Some(&orbit), Some(Vec3::new(8.0, 0.0, 0.0)), 0.0, 0.0);
+
+        assert!(comet.activity > 0.5);
+        assert!(!comet.ion_tail.particles.is_empty());
+        assert!(comet.ion_tail.particles.iter().all(|particle| particle.velocity.x < 0.0));
+    }
+
+    #[test]
+    fn stays_frozen_without_a_star() {
+        let orbit = OrbitalElements::new(10.0, 0.6, 0.0, 0.0, 0.0, 0.0).around(40.96);
+        let mut comet = Comet::new(0.1, 3, 20.0);
+        comet.update_tails(Some(&orbit), None, 0.0, 0.0);
+        assert_eq!(comet.activity, 0.0);
+        assert!(comet.ion_tail.particles.is_empty() && comet.dust_tail.particles.is_empty());
+    }
+}
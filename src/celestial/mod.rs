@@ -1,7 +1,19 @@
 pub mod ship;
 pub mod planet;
 pub mod star;
+pub mod orbit;
+pub mod spin;
+pub mod ring;
+pub mod asteroid_belt;
+pub mod comet;
+pub mod black_hole;
 
-pub use ship::Ship;
-pub use planet::{Planet, PlanetShader};
+pub use ship::{Ship, ShipControls};
+pub use planet::{Planet, PlanetPalette, PlanetShader, name_seed};
 pub use star::Star;
+pub use orbit::OrbitalElements;
+pub use spin::Spin;
+pub use ring::Ring;
+pub use asteroid_belt::AsteroidBelt;
+pub use comet::Comet;
+pub use black_hole::BlackHole;
@@ -0,0 +1,171 @@
+use nalgebra_glm::{DVec3, Vec3};
+use std::f64::consts::TAU;
+
+// Gravitational parameter of the central star in scene units, picked so the
+// innermost orbit (a = 4) keeps its old pace of roughly 0.8 rad/s
+pub const STAR_GM: f64 = 40.96;
+
+// Kepler's equation converges too slowly past this, and the orbit is a parabola at 1
+pub const MAX_ECCENTRICITY: f64 = 0.99;
+
+// Classical Keplerian elements. Angles are stored in radians. The reference
+// plane (the ecliptic) is the scene's XZ plane with its north pole along +Y and
+// the reference direction along +X; ecliptic +Y maps to scene -Z so the frame
+// stays right-handed and prograde orbits turn counterclockwise seen from above.
+#[derive(Clone, Copy, Debug)]
+pub struct OrbitalElements {
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub ascending_node: f64,
+    pub periapsis: f64,
+    pub mean_anomaly_at_epoch: f64,
+    pub mean_motion: f64,
+}
+
+impl OrbitalElements {
+    // Angles are given in degrees, the usual way orbital elements are tabulated
+    pub fn new(
+        semi_major_axis: f64,
+        eccentricity: f64,
+        inclination: f64,
+        ascending_node: f64,
+        periapsis: f64,
+        mean_anomaly_at_epoch: f64,
+    ) -> Self {
+        OrbitalElements {
+            semi_major_axis,
+            eccentricity: eccentricity.clamp(0.0, MAX_ECCENTRICITY),
+            inclination: inclination.to_radians(),
+            ascending_node: ascending_node.to_radians(),
+            periapsis: periapsis.to_radians(),
+            mean_anomaly_at_epoch: mean_anomaly_at_epoch.to_radians(),
+            mean_motion: (STAR_GM / semi_major_axis.powi(3)).sqrt(),
+        }
+    }
+
+    // Recomputes the mean motion for an orbit around a body other than the star
+    pub fn around(mut self, gm: f64) -> Self {
+        self.mean_motion = (gm / self.semi_major_axis.powi(3)).sqrt();
+        self
+    }
+
+    pub fn period(&self) -> f64 {
+        TAU / self.mean_motion
+    }
+
+    pub fn mean_anomaly(&self, time: f64) -> f64 {
+        (self.mean_anomaly_at_epoch + self.mean_motion * time).rem_euclid(TAU)
+    }
+
+    // Solves Kepler's equation M = E - e sin E with Newton's method
+    pub fn eccentric_anomaly(&self, mean_anomaly: f64) -> f64 {
+        let e = self.eccentricity;
+        let mut anomaly = if e > 0.8 { std::f64::consts::PI } else { mean_anomaly };
+
+        for _ in 0..16 {
+            let delta = (anomaly - e * anomaly.sin() - mean_anomaly) / (1.0 - e * anomaly.cos());
+            anomaly -= delta;
+            if delta.abs() < 1e-10 {
+                break;
+            }
+        }
+
+        anomaly
+    }
+
+    // Position relative to the focus after `time` seconds since the epoch
+    pub fn position_at(&self, time: f64) -> Vec3 {
+        let (position, _) = self.state_at(time);
+        Vec3::new(position.x as f32, position.y as f32, position.z as f32)
+    }
+
+    // Position and velocity relative to the focus, used to seed the n-body integrator
+    pub fn state_at(&self, time: f64) -> (DVec3, DVec3) {
+        let e = self.eccentricity;
+        let a = self.semi_major_axis;
+        let b = a * (1.0 - e * e).sqrt();
+        let anomaly = self.eccentric_anomaly(self.mean_anomaly(time));
+        let (sin_e, cos_e) = anomaly.sin_cos();
+
+        // Coordinates in the orbital plane, x pointing at periapsis
+        let x = a * (cos_e - e);
+        let y = b * sin_e;
+
+        let anomaly_rate = self.mean_motion / (1.0 - e * cos_e);
+        let vx = -a * sin_e * anomaly_rate;
+        let vy = b * cos_e * anomaly_rate;
+
+        (self.orient(x, y), self.orient(vx, vy))
+    }
+
+    // Rotates a point in the orbital plane by the periapsis argument, the
+    // inclination and the ascending node, in that order
+    fn orient(&self, x: f64, y: f64) -> DVec3 {
+        let (sin_w, cos_w) = self.periapsis.sin_cos();
+        let (sin_i, cos_i) = self.inclination.sin_cos();
+        let (sin_o, cos_o) = self.ascending_node.sin_cos();
+
+        let px = x * cos_w - y * sin_w;
+        let py = x * sin_w + y * cos_w;
+
+        let ecliptic_x = px * cos_o - py * cos_i * sin_o;
+        let ecliptic_y = px * sin_o + py * cos_i * cos_o;
+        let ecliptic_z = py * sin_i;
+
+        DVec3::new(ecliptic_x, ecliptic_z, -ecliptic_y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circular_orbits_keep_their_radius_and_pace() {
+        let orbit = OrbitalElements::new(4.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        for step in 0..12 {
+            let mean_anomaly = step as f64 * TAU / 12.0;
+            assert!((orbit.eccentric_anomaly(mean_anomaly) - mean_anomaly).abs() < 1e-12);
+            let (position, velocity) = orbit.state_at(mean_anomaly / orbit.mean_motion);
+            assert!((position.magnitude() - 4.0).abs() < 1e-9);
+            assert!((velocity.magnitude() - (STAR_GM / 4.0).sqrt()).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn solves_keplers_equation_at_high_eccentricity() {
+        let orbit = OrbitalElements::new(20.0, 0.75, 162.0, 58.0, 111.0, 330.0);
+        for step in 0..=360 {
+            let mean_anomaly = (step as f64).to_radians();
+            let anomaly = orbit.eccentric_anomaly(mean_anomaly);
+            let residual = anomaly - 0.75 * anomaly.sin() - mean_anomaly;
+            assert!(residual.abs() < 1e-9, "M = {}: residual {}", mean_anomaly, residual);
+        }
+    }
+
+    #[test]
+    fn radius_stays_between_periapsis_and_apoapsis() {
+        let orbit = OrbitalElements::new(20.0, 0.75, 162.0, 58.0, 111.0, 330.0);
+        let (periapsis, apoapsis) = (20.0 * 0.25, 20.0 * 1.75);
+        let (mut closest, mut farthest) = (f64::INFINITY, 0.0f64);
+        for step in 0..1000 {
+            let radius = orbit.state_at(orbit.period() * step as f64 / 1000.0).0.magnitude();
+            closest = closest.min(radius);
+            farthest = farthest.max(radius);
+        }
+        assert!(closest >= periapsis - 1e-9 && farthest <= apoapsis + 1e-9);
+        // Sampled finely enough to come close to both ends
+        assert!(closest - periapsis < 0.05 && apoapsis - farthest < 0.05);
+    }
+
+    #[test]
+    fn returns_to_the_start_after_a_period() {
+        for eccentricity in [0.0, 0.2, 0.75, 0.95] {
+            let orbit = OrbitalElements::new(6.0, eccentricity, 30.0, 40.0, 50.0, 60.0);
+            let start = orbit.state_at(1.5).0;
+            let end = orbit.state_at(1.5 + orbit.period()).0;
+            assert!((end - start).magnitude() < 1e-8, "e = {}", eccentricity);
+        }
+    }
+}
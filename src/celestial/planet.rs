@@ -1,56 +1,156 @@
+use crate::celestial::ring::Ring;
+use crate::celestial::spin::Spin;
+use crate::color::Color;
 use crate::geometry::{Mesh, create_sphere};
+use crate::shaders::terrain::{Terrain, TerrainKind};
 use nalgebra_glm::Vec3;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum PlanetShader {
     Rocky,
     Gaseous,
     Lava,
+    Ice,
+    Desert,
+    Ocean,
+    Cratered,
+    Terrestrial,
+}
+
+// Colors roughly ordered from the lowest terrain to the highest, plus the polar caps.
+// Each shader documents how it maps them in `default_palette`.
+#[derive(Clone, Copy, Debug)]
+pub struct PlanetPalette {
+    pub low: Color,
+    pub mid: Color,
+    pub high: Color,
+    pub polar: Color,
+}
+
+impl PlanetPalette {
+    pub const fn new(low: Color, mid: Color, high: Color, polar: Color) -> Self {
+        PlanetPalette { low, mid, high, polar }
+    }
+}
+
+impl PlanetShader {
+    pub fn default_palette(self) -> PlanetPalette {
+        match self {
+            // ocean, land, highlands, caps
+            PlanetShader::Rocky => PlanetPalette::new(
+                Color::CYAN, Color::new(0, 200, 100), Color::new(120, 90, 60), Color::WHITE,
+            ),
+            // two band colors, storms, pole haze
+            PlanetShader::Gaseous => PlanetPalette::new(
+                Color::MAGENTA, Color::PURPLE, Color::new(255, 150, 200), Color::new(90, 0, 160),
+            ),
+            // crust, lava, bright lava, unused
+            PlanetShader::Lava => PlanetPalette::new(
+                Color::new(20, 20, 20), Color::new(255, 100, 0), Color::new(255, 0, 128), Color::new(20, 20, 20),
+            ),
+            // crevasses, ice sheet, pressure ridges, caps
+            PlanetShader::Ice => PlanetPalette::new(
+                Color::new(20, 70, 140), Color::new(170, 210, 240), Color::new(225, 240, 255), Color::WHITE,
+            ),
+            // dune troughs, sand, rock outcrops, frost caps
+            PlanetShader::Desert => PlanetPalette::new(
+                Color::new(170, 100, 50), Color::new(230, 180, 110), Color::new(120, 70, 50), Color::new(240, 230, 220),
+            ),
+            // deep water, shallows, islands, sea ice
+            PlanetShader::Ocean => PlanetPalette::new(
+                Color::new(0, 30, 110), Color::new(0, 150, 200), Color::new(220, 200, 140), Color::new(230, 245, 255),
+            ),
+            // crater floors, regolith, rims and ejecta, unused
+            PlanetShader::Cratered => PlanetPalette::new(
+                Color::new(60, 60, 65), Color::new(130, 128, 125), Color::new(200, 198, 195), Color::new(130, 128, 125),
+            ),
+            // ocean, vegetation, arid land and mountains, snow
+            PlanetShader::Terrestrial => PlanetPalette::new(
+                Color::new(10, 60, 150), Color::new(40, 140, 50), Color::new(180, 150, 90), Color::WHITE,
+            ),
+        }
+    }
+
+    // Gravitational parameter per unit of radius cubed. Kept tiny next to the star's
+    // so the tightly packed planets barely perturb each other under n-body gravity;
+    // at these masses their Hill spheres are smaller than the planets themselves,
+    // so moons don't stay bound in that mode.
+    fn density(self) -> f64 {
+        match self {
+            PlanetShader::Gaseous => 0.006,
+            PlanetShader::Ice | PlanetShader::Ocean => 0.012,
+            _ => 0.02,
+        }
+    }
+
+    fn terrain_kind(self) -> Option<TerrainKind> {
+        match self {
+            PlanetShader::Rocky | PlanetShader::Ice | PlanetShader::Terrestrial => Some(TerrainKind::Mountains),
+            PlanetShader::Lava => Some(TerrainKind::Ridges),
+            PlanetShader::Cratered => Some(TerrainKind::Craters),
+            PlanetShader::Desert => Some(TerrainKind::Dunes),
+            PlanetShader::Gaseous | PlanetShader::Ocean => None,
+        }
+    }
 }
 
 pub struct Planet {
     pub position: Vec3,
     pub radius: f32,
-    pub rotation: f32,
-    pub rotation_speed: f32,
-    pub orbit_radius: f32,
-    pub orbit_angle: f32,
-    pub orbit_speed: f32,
+    pub spin: Spin,
+    // Gravitational parameter (G * mass) in scene units
+    pub mass: f64,
     pub shader_type: PlanetShader,
+    pub palette: PlanetPalette,
+    pub seed: u32,
     pub mesh: Mesh,
+    pub ring: Option<Ring>,
 }
 
 impl Planet {
-    pub fn new(shader_type: PlanetShader, radius: f32, orbit_radius: f32, orbit_speed: f32) -> Self {
-        let mesh = create_sphere(radius, 12, 12);
-
-        let orbit_angle: f32 = 0.0;
-        let position = Vec3::new(
-            orbit_radius * orbit_angle.cos(),
-            0.0,
-            orbit_radius * orbit_angle.sin(),
-        );
+    pub fn new(shader_type: PlanetShader, radius: f32, seed: u32) -> Self {
+        // Displaced planets need a denser mesh so the relief shows on the silhouette
+        let mesh = if shader_type.terrain_kind().is_some() {
+            create_sphere(radius, 24, 24)
+        } else {
+            create_sphere(radius, 12, 12)
+        };
 
         Planet {
-            position,
+            position: Vec3::zeros(),
             radius,
-            rotation: 0.0,
-            rotation_speed: 0.1,
-            orbit_radius,
-            orbit_angle,
-            orbit_speed,
+            spin: Spin::new(0.0, 60.0),
+            mass: shader_type.density() * (radius as f64).powi(3),
             shader_type,
+            palette: shader_type.default_palette(),
+            seed,
             mesh,
+            ring: None,
         }
     }
 
     pub fn update(&mut self, delta_time: f32) {
-        self.rotation += self.rotation_speed * delta_time;
+        self.spin.update(delta_time);
+    }
 
-        self.orbit_angle += self.orbit_speed * delta_time;
+    pub fn terrain(&self) -> Option<Terrain> {
+        let kind = self.shader_type.terrain_kind()?;
+        let amplitude = match kind {
+            TerrainKind::Mountains => 0.08,
+            TerrainKind::Ridges => 0.06,
+            TerrainKind::Craters => 0.05,
+            TerrainKind::Dunes => 0.015,
+        };
 
-        self.position.x = self.orbit_radius * self.orbit_angle.cos();
-        self.position.z = self.orbit_radius * self.orbit_angle.sin();
-        self.position.y = 0.0;
+        Some(Terrain::new(kind, self.radius * amplitude))
     }
 }
+
+// Seed for a body that has only its name to tell it apart, so two planets of
+// the same size and type still get their own surfaces. FNV-1a, which unlike
+// the standard hasher gives the same value on every build.
+pub fn name_seed(name: &str) -> u32 {
+    name.bytes().fold(0x811c_9dc5, |hash: u32, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193))
+}
@@ -0,0 +1,30 @@
+use crate::color::Color;
+use crate::geometry::{Mesh, create_ring};
+use crate::shaders::ring_shader::RingBands;
+
+// Planetary ring around a body's equator; radii are in scene units from its centre
+pub struct Ring {
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub color: Color,
+    pub mesh: Mesh,
+}
+
+impl Ring {
+    pub fn new(inner_radius: f32, outer_radius: f32, color: Color) -> Self {
+        Ring {
+            inner_radius,
+            outer_radius,
+            color,
+            mesh: create_ring(inner_radius, outer_radius, 96),
+        }
+    }
+
+    pub fn bands(&self) -> RingBands {
+        RingBands {
+            inner_radius: self.inner_radius,
+            outer_radius: self.outer_radius,
+            color: self.color,
+        }
+    }
+}
@@ -1,53 +1,173 @@
 use crate::geometry::{Mesh, load_obj};
-use crate::camera::Camera;
-use nalgebra_glm::{Vec3, Mat4};
-use std::f32::consts::PI;
+use nalgebra_glm::{Vec3, Mat4, Quat};
 
+// Ship space axes, matching the camera's: right is +X, up is +Y, the nose points down -Z
+const LOCAL_RIGHT: Vec3 = Vec3::new(1.0, 0.0, 0.0);
+const LOCAL_UP: Vec3 = Vec3::new(0.0, 1.0, 0.0);
+const LOCAL_FORWARD: Vec3 = Vec3::new(0.0, 0.0, -1.0);
+
+// Thrust multiplier while the warp is engaged
+const BOOST: f32 = 5.0;
+// Seconds flight assist takes to cancel most of a drift or a spin
+const ASSIST_TIME: f32 = 0.6;
+// How far outside a body's surface the hull stops, in its radii
+const HULL_MARGIN: f32 = 0.05;
+// Longest simulated step the flight is integrated in, and how many of them a
+// single update may take; under heavier warp the ship's own time falls behind
+// the bodies' rather than its steps growing unstable
+const MAX_STEP: f32 = 1.0 / 60.0;
+const MAX_SUBSTEPS: usize = 200;
+
+// What the pilot asks for this frame, each component from -1 to 1
+#[derive(Clone, Copy, Default)]
+pub struct ShipControls {
+    // Along the ship's right, up and forward axes
+    pub thrust: Vec3,
+    // Pitch up, yaw left and roll right, as fractions of the RCS authority
+    pub rotation: Vec3,
+    pub boost: bool,
+}
+
+// A rigid body with main engines and RCS thrusters. It keeps its momentum,
+// falls towards whatever has mass, and only turns or changes course when the
+// thrusters fire; flight assist fires them for the pilot to stop drifting and
+// spinning when the controls are let go. Its course runs on simulated time,
+// like the bodies it falls towards; its turns on real time, like the pilot.
+// The autopilot bypasses all of this and moves the ship directly.
 pub struct Ship {
     pub mesh: Mesh,
     pub position: Vec3,
-    pub rotation: Vec3,
+    pub velocity: Vec3,
+    pub orientation: Quat,
+    // Radians per second around the ship's own right, up and forward axes
+    pub angular_velocity: Vec3,
     pub scale: f32,
+    pub mass: f32,
+    // Force of the engines at full throttle, in any direction
+    pub max_thrust: f32,
+    // Angular acceleration the RCS can give, in radians per second squared
+    pub rcs_acceleration: f32,
+    pub max_turn_rate: f32,
+    pub flight_assist: bool,
+    // Turns the model so its nose, along -X in the OBJ file, points down -Z
+    model_alignment: Quat,
 }
 
 impl Ship {
     pub fn new(obj_path: &str) -> Result<Self, String> {
-        let mesh = load_obj(obj_path)?;
+        Ok(Self::from_mesh(load_obj(obj_path)?))
+    }
 
-        Ok(Ship {
+    pub fn from_mesh(mesh: Mesh) -> Self {
+        Ship {
             mesh,
             position: Vec3::new(0.0, 0.0, 0.0),
-            rotation: Vec3::new(0.0, 0.0, 0.0),
+            velocity: Vec3::zeros(),
+            orientation: Quat::identity(),
+            angular_velocity: Vec3::zeros(),
             scale: 1.0,
-        })
+            mass: 1.0,
+            max_thrust: 2.0,
+            rcs_acceleration: 4.0,
+            max_turn_rate: 1.5,
+            flight_assist: true,
+            model_alignment: nalgebra_glm::quat_angle_axis(-std::f32::consts::FRAC_PI_2, &LOCAL_UP),
+        }
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        nalgebra_glm::quat_rotate_vec3(&self.orientation, &LOCAL_FORWARD)
+    }
+
+    pub fn right(&self) -> Vec3 {
+        nalgebra_glm::quat_rotate_vec3(&self.orientation, &LOCAL_RIGHT)
+    }
+
+    pub fn up(&self) -> Vec3 {
+        nalgebra_glm::quat_rotate_vec3(&self.orientation, &LOCAL_UP)
+    }
+
+    // Advances the course by `delta_time` simulated seconds under the pilot's
+    // controls and `gravity`, the acceleration the bodies pull with at a point
+    pub fn update(&mut self, controls: &ShipControls, gravity: impl Fn(&Vec3) -> Vec3, delta_time: f32) {
+        let substeps = ((delta_time / MAX_STEP).ceil() as usize).clamp(1, MAX_SUBSTEPS);
+        let h = (delta_time / substeps as f32).min(MAX_STEP);
+        for _ in 0..substeps {
+            self.fly(controls, gravity(&self.position), h);
+        }
     }
 
-    pub fn update(&mut self, camera: &Camera) {
-        let forward = camera.forward();
-        let right = camera.right();
-        let up = Vec3::new(0.0, 1.0, 0.0);
+    fn fly(&mut self, controls: &ShipControls, gravity: Vec3, delta_time: f32) {
+        let boost = if controls.boost { BOOST } else { 1.0 };
+        let max_acceleration = self.max_thrust * boost / self.mass;
+        let axes = [self.right(), self.up(), self.forward()];
+        let mut thrust = Vec3::zeros();
+        for (i, axis) in axes.iter().enumerate() {
+            thrust += axis * controls.thrust[i].clamp(-1.0, 1.0);
+        }
+        let mut acceleration = thrust * max_acceleration;
 
-        let offset_forward = 2.0;
-        let offset_right = 0.0;
-        let offset_up = -0.5;
+        // Flight assist holds the ship still on every axis the pilot leaves
+        // alone, working against gravity too, within what the engines can do
+        if self.flight_assist {
+            let wanted = -self.velocity / ASSIST_TIME - gravity;
+            for (i, axis) in axes.iter().enumerate() {
+                if controls.thrust[i] == 0.0 {
+                    acceleration += axis * wanted.dot(axis);
+                }
+            }
+            if acceleration.magnitude() > max_acceleration {
+                acceleration = acceleration.normalize() * max_acceleration;
+            }
+        }
 
-        self.position = camera.eye
-            + forward * offset_forward
-            + right * offset_right
-            + up * offset_up;
+        // Semi-implicit Euler, stable enough at frame-sized steps
+        self.velocity += (acceleration + gravity) * delta_time;
+        self.position += self.velocity * delta_time;
+    }
+
+    // Fires the RCS for `delta_time` real seconds
+    pub fn steer(&mut self, controls: &ShipControls, delta_time: f32) {
+        let max_step = self.rcs_acceleration * delta_time;
+        for i in 0..3 {
+            let input = controls.rotation[i].clamp(-1.0, 1.0);
+            let spin = &mut self.angular_velocity[i];
+            if input != 0.0 {
+                *spin = (*spin + input * max_step).clamp(-self.max_turn_rate, self.max_turn_rate);
+            } else if self.flight_assist {
+                // Counter-fire until the spin stops, without overshooting
+                *spin -= spin.clamp(-max_step, max_step);
+            }
+        }
 
-        let yaw = forward.z.atan2(forward.x);
-        self.rotation.y = yaw;
+        let [pitch, yaw, roll] = [self.angular_velocity.x, self.angular_velocity.y, self.angular_velocity.z];
+        let turn = nalgebra_glm::quat_angle_axis(yaw * delta_time, &LOCAL_UP)
+            * nalgebra_glm::quat_angle_axis(pitch * delta_time, &LOCAL_RIGHT)
+            * nalgebra_glm::quat_angle_axis(roll * delta_time, &LOCAL_FORWARD);
+        self.orientation = nalgebra_glm::quat_normalize(&(self.orientation * turn));
+    }
+
+    // Keeps the hull outside a sphere, stopping any motion into it
+    pub fn collide(&mut self, center: &Vec3, radius: f32) {
+        let offset = self.position - center;
+        let distance = offset.magnitude();
+        let surface = radius * (1.0 + HULL_MARGIN);
+        if distance >= surface || distance < 1e-6 {
+            return;
+        }
+        let normal = offset / distance;
+        self.position = center + normal * surface;
+        let inward = self.velocity.dot(&normal);
+        if inward < 0.0 {
+            self.velocity -= normal * inward;
+        }
     }
 
     pub fn get_model_matrix(&self) -> Mat4 {
         let translation = nalgebra_glm::translate(&Mat4::identity(), &self.position);
         let scale_mat = nalgebra_glm::scale(&Mat4::identity(), &Vec3::new(self.scale, self.scale, self.scale));
-        
-        let rotation_x = nalgebra_glm::rotate(&Mat4::identity(), self.rotation.x, &Vec3::new(1.0, 0.0, 0.0));
-        let rotation_y = nalgebra_glm::rotate(&Mat4::identity(), self.rotation.y, &Vec3::new(0.0, 1.0, 0.0));
-        let rotation_z = nalgebra_glm::rotate(&Mat4::identity(), self.rotation.z, &Vec3::new(0.0, 0.0, 1.0));
-        
-        translation * rotation_z * rotation_y * rotation_x * scale_mat
+        let rotation = nalgebra_glm::quat_to_mat4(&(self.orientation * self.model_alignment));
+
+        translation * rotation * scale_mat
     }
 }
@@ -0,0 +1,45 @@
+use crate::celestial::OrbitalElements;
+use nalgebra_glm::{Mat4, Vec3};
+use std::f32::consts::TAU;
+
+// Rotation about a single axis. The axis is tipped from the orbital pole (+Y)
+// towards +X by the axial tilt and keeps pointing the same way all year long,
+// which is what gives tilted planets their seasons.
+#[derive(Clone, Copy, Debug)]
+pub struct Spin {
+    pub axial_tilt: f32,
+    pub rotation_period: f32,
+    pub angle: f32,
+}
+
+impl Spin {
+    // Tilt in degrees and sidereal day in seconds; a negative period spins retrograde
+    pub fn new(axial_tilt: f32, rotation_period: f32) -> Self {
+        Spin {
+            axial_tilt: axial_tilt.to_radians(),
+            rotation_period,
+            angle: 0.0,
+        }
+    }
+
+    // Tidally locked: one turn per orbit, in the same sense as the orbit, so the
+    // same face keeps pointing at the parent. The spin turns about +Y, and an
+    // orbit whose pole points below the reference plane (inclination over 90
+    // degrees, like Triton's) runs the other way round.
+    pub fn locked(axial_tilt: f32, orbit: &OrbitalElements) -> Self {
+        let sense = if orbit.inclination.cos() < 0.0 { -1.0 } else { 1.0 };
+        Spin::new(axial_tilt, orbit.period() as f32 * sense)
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        if self.rotation_period != 0.0 {
+            self.angle = (self.angle + TAU * delta_time / self.rotation_period).rem_euclid(TAU);
+        }
+    }
+
+    // Object space to the body's tilted, spinning frame
+    pub fn matrix(&self) -> Mat4 {
+        let tilt = nalgebra_glm::rotate(&Mat4::identity(), -self.axial_tilt, &Vec3::new(0.0, 0.0, 1.0));
+        nalgebra_glm::rotate(&tilt, self.angle, &Vec3::new(0.0, 1.0, 0.0))
+    }
+}
@@ -1,11 +1,73 @@
+use crate::celestial::orbit::STAR_GM;
+use crate::celestial::spin::Spin;
+use crate::color::Color;
 use crate::geometry::{Mesh, create_sphere};
 use nalgebra_glm::Vec3;
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StellarClass {
+    O,
+    B,
+    A,
+    F,
+    G,
+    K,
+    M,
+}
+
+impl StellarClass {
+    // Typical effective surface temperature in Kelvin
+    pub fn temperature(self) -> f32 {
+        match self {
+            StellarClass::O => 40000.0,
+            StellarClass::B => 20000.0,
+            StellarClass::A => 8500.0,
+            StellarClass::F => 6500.0,
+            StellarClass::G => 5800.0,
+            StellarClass::K => 4500.0,
+            StellarClass::M => 3200.0,
+        }
+    }
+
+    // Hotter to cooler, wrapping back to O after M
+    pub fn next(self) -> Self {
+        match self {
+            StellarClass::O => StellarClass::B,
+            StellarClass::B => StellarClass::A,
+            StellarClass::A => StellarClass::F,
+            StellarClass::F => StellarClass::G,
+            StellarClass::G => StellarClass::K,
+            StellarClass::K => StellarClass::M,
+            StellarClass::M => StellarClass::O,
+        }
+    }
+
+    pub fn from_temperature(kelvin: f32) -> Self {
+        if kelvin >= 30000.0 {
+            StellarClass::O
+        } else if kelvin >= 10000.0 {
+            StellarClass::B
+        } else if kelvin >= 7500.0 {
+            StellarClass::A
+        } else if kelvin >= 6000.0 {
+            StellarClass::F
+        } else if kelvin >= 5200.0 {
+            StellarClass::G
+        } else if kelvin >= 3700.0 {
+            StellarClass::K
+        } else {
+            StellarClass::M
+        }
+    }
+}
+
 pub struct Star {
     pub position: Vec3,
     pub radius: f32,
-    pub rotation: f32,
-    pub rotation_speed: f32,
+    pub spin: Spin,
+    pub mass: f64,
+    pub temperature: f32,
+    pub seed: u32,
     pub mesh: Mesh,
 }
 
@@ -16,13 +78,32 @@ impl Star {
         Star {
             position,
             radius,
-            rotation: 0.0,
-            rotation_speed: 0.05,
+            spin: Spin::new(7.0, 125.0),
+            mass: STAR_GM,
+            temperature: StellarClass::G.temperature(),
+            seed: 42,
             mesh,
         }
     }
 
     pub fn update(&mut self, delta_time: f32) {
-        self.rotation += self.rotation_speed * delta_time;
+        self.spin.update(delta_time);
+    }
+
+    pub fn class(&self) -> StellarClass {
+        StellarClass::from_temperature(self.temperature)
+    }
+
+    pub fn set_class(&mut self, class: StellarClass) {
+        self.temperature = class.temperature();
+    }
+
+    pub fn light_color(&self) -> Color {
+        Color::from_temperature(self.temperature)
+    }
+
+    // Output relative to the default Sun (radius 1.5, 5800 K), from the Stefan-Boltzmann law
+    pub fn luminosity(&self) -> f32 {
+        (self.radius / 1.5).powi(2) * (self.temperature / 5800.0).powi(4)
     }
 }
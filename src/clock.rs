@@ -0,0 +1,198 @@
+use std::time::Instant;
+
+const TIME_SCALES: [f64; 7] = [1.0, 10.0, 100.0, 1_000.0, 10_000.0, 100_000.0, 1_000_000.0];
+
+// Longest real frame we try to catch up on, so a stall doesn't trigger a burst of steps
+const MAX_FRAME_TIME: f64 = 0.25;
+
+// Shader time wraps around so noise inputs keep their f32 precision at high warp
+const SHADER_TIME_PERIOD: f64 = 10_000.0;
+
+// Simulation time, advanced in fixed real-time steps and scaled by the time warp.
+// Everything that moves with the simulation (orbits, spin, shader animation)
+// should read from here instead of the wall clock.
+pub struct SimulationClock {
+    pub time: f64,
+    pub fixed_step: f64,
+    pub paused: bool,
+    pub reversed: bool,
+    scale_index: usize,
+    accumulator: f64,
+    last_tick: Instant,
+    real_delta: f64,
+}
+
+impl SimulationClock {
+    pub fn new(fixed_step: f64) -> Self {
+        SimulationClock {
+            time: 0.0,
+            fixed_step,
+            paused: false,
+            reversed: false,
+            scale_index: 0,
+            accumulator: 0.0,
+            last_tick: Instant::now(),
+            real_delta: 0.0,
+        }
+    }
+
+    // Call once per frame before draining the steps
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        self.real_delta = (now - self.last_tick).as_secs_f64().min(MAX_FRAME_TIME);
+        self.last_tick = now;
+
+        if !self.paused {
+            self.accumulator += self.real_delta;
+        }
+    }
+
+    // Next simulation step of this frame at `time_scale`, usually the warp
+    // picked with faster and slower, in simulated seconds (negative when reversed)
+    pub fn step_at(&mut self, time_scale: f64) -> Option<f64> {
+        if self.paused || self.accumulator < self.fixed_step {
+            return None;
+        }
+        self.accumulator -= self.fixed_step;
+
+        let direction = if self.reversed { -1.0 } else { 1.0 };
+        let delta = self.fixed_step * time_scale * direction;
+        self.time += delta;
+        Some(delta)
+    }
+
+    // Wall-clock seconds since the last tick, for effects that shouldn't follow the warp
+    pub fn real_delta(&self) -> f32 {
+        self.real_delta as f32
+    }
+
+    pub fn shader_time(&self) -> f32 {
+        shader_time(self.time)
+    }
+
+    pub fn time_scale(&self) -> f64 {
+        TIME_SCALES[self.scale_index]
+    }
+
+    // Steps up the warp unless that would pass `limit`; false if it was held back
+    pub fn faster(&mut self, limit: f64) -> bool {
+        let next = (self.scale_index + 1).min(TIME_SCALES.len() - 1);
+        if TIME_SCALES[next] > limit {
+            return false;
+        }
+        self.scale_index = next;
+        true
+    }
+
+    // Drops to the fastest warp within `limit`; false if it was already there
+    pub fn limit_scale(&mut self, limit: f64) -> bool {
+        let before = self.scale_index;
+        while self.scale_index > 0 && self.time_scale() > limit {
+            self.scale_index -= 1;
+        }
+        self.scale_index != before
+    }
+
+    // Fastest warp at which each step stays within `max_delta` simulated seconds
+    pub fn scale_limit(&self, max_delta: f64) -> f64 {
+        max_delta / self.fixed_step
+    }
+
+    pub fn slower(&mut self) {
+        self.scale_index = self.scale_index.saturating_sub(1);
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    pub fn toggle_reverse(&mut self) {
+        self.reversed = !self.reversed;
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "x{}{}{}",
+            self.time_scale(),
+            if self.reversed { " reversed" } else { "" },
+            if self.paused { " (paused)" } else { "" },
+        )
+    }
+}
+
+pub fn shader_time(time: f64) -> f32 {
+    time.rem_euclid(SHADER_TIME_PERIOD) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drain(clock: &mut SimulationClock) -> Vec<f64> {
+        std::iter::from_fn(|| clock.step_at(clock.time_scale())).collect()
+    }
+
+    #[test]
+    fn takes_whole_fixed_steps_and_carries_the_rest() {
+        let mut clock = SimulationClock::new(0.01);
+        clock.accumulator = 0.035;
+        assert_eq!(drain(&mut clock).len(), 3);
+        assert!((clock.accumulator - 0.005).abs() < 1e-12);
+        clock.accumulator += 0.006;
+        assert_eq!(drain(&mut clock).len(), 1);
+        assert!((clock.time - 0.04).abs() < 1e-12);
+    }
+
+    #[test]
+    fn steps_scale_with_the_warp_and_run_backwards_when_reversed() {
+        let mut clock = SimulationClock::new(0.01);
+        assert!(clock.faster(f64::INFINITY));
+        assert!(clock.faster(f64::INFINITY));
+        clock.accumulator = 0.02;
+        assert_eq!(drain(&mut clock), vec![1.0, 1.0]);
+
+        clock.toggle_reverse();
+        clock.accumulator = 0.01;
+        assert_eq!(drain(&mut clock), vec![-1.0]);
+        assert!((clock.time - 1.0).abs() < 1e-12);
+
+        clock.toggle_pause();
+        clock.accumulator = 0.05;
+        assert!(drain(&mut clock).is_empty());
+    }
+
+    #[test]
+    fn warp_steps_stay_within_the_table() {
+        let mut clock = SimulationClock::new(0.01);
+        clock.slower();
+        assert_eq!(clock.time_scale(), 1.0);
+        while clock.faster(f64::INFINITY) && clock.time_scale() < 1_000_000.0 {}
+        assert_eq!(clock.time_scale(), 1_000_000.0);
+        clock.faster(f64::INFINITY);
+        assert_eq!(clock.time_scale(), 1_000_000.0);
+        clock.slower();
+        assert_eq!(clock.time_scale(), 100_000.0);
+    }
+
+    #[test]
+    fn faster_refuses_to_pass_the_limit() {
+        let mut clock = SimulationClock::new(0.01);
+        assert!(clock.faster(100.0));
+        assert!(clock.faster(100.0));
+        assert!(!clock.faster(100.0));
+        assert_eq!(clock.time_scale(), 100.0);
+    }
+
+    #[test]
+    fn limit_scale_drops_to_the_fastest_allowed_warp() {
+        let mut clock = SimulationClock::new(0.01);
+        for _ in 0..5 {
+            clock.faster(f64::INFINITY);
+        }
+        assert_eq!(clock.time_scale(), 100_000.0);
+        // An n-body max_time_step of 8 allows up to x800 at 0.01 s steps
+        assert!(clock.limit_scale(clock.scale_limit(8.0)));
+        assert_eq!(clock.time_scale(), 100.0);
+        assert!(!clock.limit_scale(clock.scale_limit(8.0)));
+    }
+}
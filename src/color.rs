@@ -20,7 +20,15 @@ impl Color {
         }
     }
 
-    pub fn to_hex(&self) -> u32 {
+    pub fn from_hex(hex: u32) -> Self {
+        Color {
+            r: ((hex >> 16) & 0xFF) as u8,
+            g: ((hex >> 8) & 0xFF) as u8,
+            b: (hex & 0xFF) as u8,
+        }
+    }
+
+    pub fn to_hex(self) -> u32 {
         ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
     }
 
@@ -33,16 +41,38 @@ impl Color {
         }
     }
 
+    // Approximate blackbody color for a temperature in Kelvin (Tanner Helland's fit
+    // of the CIE data), valid from roughly 1000 K to 40000 K.
+    pub fn from_temperature(kelvin: f32) -> Self {
+        let t = kelvin.clamp(1000.0, 40000.0) / 100.0;
+
+        let r = if t <= 66.0 {
+            255.0
+        } else {
+            329.698_73 * (t - 60.0).powf(-0.133_204_76)
+        };
+        let g = if t <= 66.0 {
+            99.470_8 * t.ln() - 161.119_57
+        } else {
+            288.122_17 * (t - 60.0).powf(-0.075_514_85)
+        };
+        let b = if t >= 66.0 {
+            255.0
+        } else if t <= 19.0 {
+            0.0
+        } else {
+            138.517_73 * (t - 10.0).ln() - 305.044_8
+        };
+
+        Color::from_float(r / 255.0, g / 255.0, b / 255.0)
+    }
+
     pub const BLACK: Color = Color::new(0, 0, 0);
     pub const WHITE: Color = Color::new(255, 255, 255);
-    pub const RED: Color = Color::new(255, 0, 0);
-    pub const GREEN: Color = Color::new(0, 255, 0);
-    pub const BLUE: Color = Color::new(0, 0, 255);
     
     // Retro colors (Synthwave palette)
     pub const MAGENTA: Color = Color::new(255, 0, 110);
     pub const CYAN: Color = Color::new(0, 245, 255);
-    pub const NEON_YELLOW: Color = Color::new(255, 234, 0);
     pub const PURPLE: Color = Color::new(139, 0, 255);
     pub const DARK_BG: Color = Color::new(10, 10, 31);
 }
@@ -70,3 +100,15 @@ impl Mul<f32> for Color {
         )
     }
 }
+
+impl Mul<Color> for Color {
+    type Output = Color;
+
+    fn mul(self, other: Color) -> Color {
+        Color {
+            r: ((self.r as u16 * other.r as u16) / 255) as u8,
+            g: ((self.g as u16 * other.g as u16) / 255) as u8,
+            b: ((self.b as u16 * other.b as u16) / 255) as u8,
+        }
+    }
+}
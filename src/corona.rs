@@ -0,0 +1,74 @@
+use crate::celestial::Star;
+use crate::framebuffer::Framebuffer;
+use crate::shaders::planet_shaders::smoothstep;
+use crate::uniforms::Uniforms;
+use nalgebra_glm::Vec3;
+
+// Camera-facing glow drawn around a star after the scene. It is rasterized
+// directly in screen space at the star's depth, so closer bodies hide it.
+pub struct Corona {
+    pub extent: f32,
+    pub intensity: f32,
+}
+
+impl Corona {
+    pub fn new() -> Self {
+        Corona {
+            extent: 3.0,
+            intensity: 0.9,
+        }
+    }
+
+    pub fn render(&self, star: &Star, framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
+        let Some(center) = uniforms.world_to_screen(&star.position) else {
+            return;
+        };
+        let edge_point = star.position + uniforms.camera_right() * star.radius;
+        let Some(edge) = uniforms.world_to_screen(&edge_point) else {
+            return;
+        };
+
+        let radius_px = (edge.xy() - center.xy()).magnitude();
+        if radius_px < 0.5 {
+            return;
+        }
+
+        let reach = radius_px * self.extent;
+        let min_x = (center.x - reach).max(0.0) as usize;
+        let min_y = (center.y - reach).max(0.0) as usize;
+        let max_x = (center.x + reach).min(framebuffer.width as f32 - 1.0);
+        let max_y = (center.y + reach).min(framebuffer.height as f32 - 1.0);
+        if max_x < 0.0 || max_y < 0.0 {
+            return;
+        }
+
+        let noise = &uniforms.noise;
+        let time = uniforms.time;
+        let tint = star.light_color();
+
+        for y in min_y..=max_y as usize {
+            for x in min_x..=max_x as usize {
+                let dx = x as f32 + 0.5 - center.x;
+                let dy = y as f32 + 0.5 - center.y;
+                let r = (dx * dx + dy * dy).sqrt() / radius_px;
+                if !(0.95..self.extent).contains(&r) {
+                    continue;
+                }
+
+                // Streamers: angular noise that slowly twists and flickers with time
+                let angle = dy.atan2(dx);
+                let around = Vec3::new(angle.cos() * 2.5, angle.sin() * 2.5, time * 0.08);
+                let streamers = noise.fbm(&(around + Vec3::new(0.0, 0.0, r * 0.6))) * 0.5 + 0.5;
+                let rays = smoothstep(0.35, 0.85, streamers);
+
+                let falloff = (1.0 - (r - 1.0) / (self.extent - 1.0)).max(0.0);
+                let glow = falloff.powi(3) * 0.6 + falloff.powi(2) * rays * 0.7;
+
+                let amount = glow * self.intensity * smoothstep(0.95, 1.05, r);
+                if amount > 0.004 {
+                    framebuffer.blend_add(x, y, center.z, &(tint * amount));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use crate::scene::{Body, NodeId, Scene};
+use nalgebra_glm::DVec3;
+
+// Samples per orbit of the fastest body involved; short enough to catch a
+// transit across the star, which only lasts a small fraction of an orbit
+const SAMPLES_PER_PERIOD: f64 = 1000.0;
+// Upper bound on the samples one pair may take while looking ahead
+const MAX_SAMPLES: usize = 250_000;
+const REFINE_STEPS: usize = 40;
+// How far ahead to look at most: a Julian year, in seconds
+const MAX_HORIZON: f64 = 365.25 * 86_400.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EclipseKind {
+    // Somewhere on the target the light is completely covered
+    Total,
+    // The caster is too small to cover the light, leaving a bright ring
+    Annular,
+    // The target only reaches the penumbra
+    Partial,
+    // The caster is a small dot crossing the light, as seen from the target
+    Transit,
+}
+
+impl EclipseKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            EclipseKind::Total => "Total",
+            EclipseKind::Annular => "Annular",
+            EclipseKind::Partial => "Partial",
+            EclipseKind::Transit => "Transit",
+        }
+    }
+}
+
+// The umbra and penumbra cones a spherical body casts away from a spherical light
+pub struct Shadow {
+    caster: DVec3,
+    caster_radius: f64,
+    light_radius: f64,
+    // From the light towards the caster
+    axis: DVec3,
+    distance: f64,
+}
+
+impl Shadow {
+    pub fn new(light: DVec3, light_radius: f64, caster: DVec3, caster_radius: f64) -> Self {
+        let offset = caster - light;
+        let distance = offset.norm().max(1e-9);
+        Shadow {
+            caster,
+            caster_radius,
+            light_radius,
+            axis: offset / distance,
+            distance,
+        }
+    }
+
+    // Distance behind the caster along the axis, and from the axis
+    fn locate(&self, point: DVec3) -> (f64, f64) {
+        let offset = point - self.caster;
+        let behind = offset.dot(&self.axis);
+        (behind, (offset - self.axis * behind).norm())
+    }
+
+    // Negative past the umbra's tip, where the antumbra takes over
+    fn umbra_radius(&self, behind: f64) -> f64 {
+        self.caster_radius - behind * (self.light_radius - self.caster_radius) / self.distance
+    }
+
+    fn penumbra_radius(&self, behind: f64) -> f64 {
+        self.caster_radius + behind * (self.light_radius + self.caster_radius) / self.distance
+    }
+
+    // How far a sphere is from the penumbra: below 1 it is at least partly inside
+    pub fn depth(&self, target: DVec3, target_radius: f64) -> f64 {
+        let (behind, across) = self.locate(target);
+        if behind <= 0.0 {
+            return f64::INFINITY;
+        }
+        across / (self.penumbra_radius(behind) + target_radius)
+    }
+
+    pub fn classify(&self, target: DVec3, target_radius: f64) -> Option<EclipseKind> {
+        let (behind, across) = self.locate(target);
+        if behind <= 0.0 || across >= self.penumbra_radius(behind) + target_radius {
+            return None;
+        }
+
+        // Compare apparent sizes as seen from the target
+        let caster_size = self.caster_radius / behind;
+        let light_size = self.light_radius / (self.distance + behind);
+        if caster_size < 0.5 * light_size {
+            return Some(EclipseKind::Transit);
+        }
+
+        let umbra = self.umbra_radius(behind);
+        if across < umbra.abs() + target_radius {
+            Some(if umbra > 0.0 { EclipseKind::Total } else { EclipseKind::Annular })
+        } else {
+            Some(EclipseKind::Partial)
+        }
+    }
+}
+
+// A body that may shade another from one light
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EclipsePair {
+    pub caster: NodeId,
+    pub target: NodeId,
+    pub light: NodeId,
+}
+
+impl EclipsePair {
+    fn shadow_at(&self, scene: &Scene, time: f64) -> (Shadow, DVec3, f64) {
+        let position = |id| scene.kepler_state_at(id, time).0;
+        let radius = |id: NodeId| scene.nodes[id].body.radius() as f64;
+        let shadow = Shadow::new(position(self.light), radius(self.light), position(self.caster), radius(self.caster));
+        (shadow, position(self.target), radius(self.target))
+    }
+
+    // Where the bodies are right now, which also follows the n-body simulation
+    pub fn classify_now(&self, scene: &Scene) -> Option<EclipseKind> {
+        let position = |id: NodeId| {
+            let p = scene.nodes[id].world_position;
+            DVec3::new(p.x as f64, p.y as f64, p.z as f64)
+        };
+        let radius = |id: NodeId| scene.nodes[id].body.radius() as f64;
+        let shadow = Shadow::new(position(self.light), radius(self.light), position(self.caster), radius(self.caster));
+        shadow.classify(position(self.target), radius(self.target))
+    }
+
+    fn classify_at(&self, scene: &Scene, time: f64) -> Option<EclipseKind> {
+        let (shadow, target, radius) = self.shadow_at(scene, time);
+        shadow.classify(target, radius)
+    }
+
+    fn depth_at(&self, scene: &Scene, time: f64) -> f64 {
+        let (shadow, target, radius) = self.shadow_at(scene, time);
+        shadow.depth(target, radius)
+    }
+
+    // Shortest orbital period among the bodies involved, if any of them moves
+    fn fastest_period(&self, scene: &Scene) -> Option<f64> {
+        [self.caster, self.target, self.light]
+            .iter()
+            .filter_map(|&id| scene.nodes[id].orbit.as_ref())
+            .map(|orbit| orbit.period())
+            .filter(|period| period.is_finite() && *period > 0.0)
+            .reduce(f64::min)
+    }
+
+    pub fn describe(&self, scene: &Scene, kind: EclipseKind) -> String {
+        let name = |id: NodeId| scene.nodes[id].name.as_str();
+        match kind {
+            EclipseKind::Transit => format!("Transit of {} across {} seen from {}", name(self.caster), name(self.light), name(self.target)),
+            _ => format!(
+                "{} eclipse of {} by {} on {}",
+                kind.name(),
+                name(self.light),
+                name(self.caster),
+                name(self.target)
+            ),
+        }
+    }
+}
+
+// Every combination worth watching: planets and moons shading their siblings,
+// a moon shading its planet and the other way round, under every star
+pub fn pairs(scene: &Scene) -> Vec<EclipsePair> {
+    let related = |a: NodeId, b: NodeId| {
+        let (a_parent, b_parent) = (scene.nodes[a].parent, scene.nodes[b].parent);
+        a_parent == b_parent || a_parent == Some(b) || b_parent == Some(a)
+    };
+
+    let mut pairs = Vec::new();
+    for (light, light_node) in scene.nodes.iter().enumerate() {
+        if !matches!(light_node.body, Body::Star(_)) {
+            continue;
+        }
+        for (caster, caster_node) in scene.nodes.iter().enumerate() {
+            if caster == light || !matches!(caster_node.body, Body::Planet(_) | Body::Star(_)) {
+                continue;
+            }
+            for (target, target_node) in scene.nodes.iter().enumerate() {
+                if target == caster || !matches!(target_node.body, Body::Planet(_)) || !related(caster, target) {
+                    continue;
+                }
+                pairs.push(EclipsePair { caster, target, light });
+            }
+        }
+    }
+    pairs
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EclipseEvent {
+    pub pair: EclipsePair,
+    // The deepest the target gets into the shadow
+    pub kind: EclipseKind,
+    pub start: f64,
+    pub peak: f64,
+    pub end: f64,
+}
+
+// Eclipses and transits between `from` and `from + horizon`, in order, found
+// from the orbits alone. Under n-body physics they drift away from these times.
+pub fn predict(scene: &Scene, from: f64, horizon: f64) -> Vec<EclipseEvent> {
+    let mut events = Vec::new();
+
+    for pair in pairs(scene) {
+        let Some(period) = pair.fastest_period(scene) else {
+            continue;
+        };
+        let step = (period / SAMPLES_PER_PERIOD).max(horizon / MAX_SAMPLES as f64);
+        let inside = |time: f64| pair.classify_at(scene, time).is_some();
+
+        let mut time = from;
+        let mut was_inside = inside(time);
+        // An eclipse already under way counts from now
+        let mut start = was_inside.then_some(from);
+        while time < from + horizon {
+            let next = (time + step).min(from + horizon);
+            let now_inside = inside(next);
+            match (was_inside, now_inside) {
+                (false, true) => start = Some(refine(time, next, &inside)),
+                (true, false) => {
+                    let end = refine(time, next, &|time| !inside(time));
+                    if let Some(start) = start.take() {
+                        events.push(event(scene, pair, start, end));
+                    }
+                }
+                _ => {}
+            }
+            was_inside = now_inside;
+            time = next;
+        }
+        if let Some(start) = start {
+            events.push(event(scene, pair, start, from + horizon));
+        }
+    }
+
+    events.sort_by(|a, b| a.start.total_cmp(&b.start));
+    events
+}
+
+// One orbit of the slowest planet, so every pairing comes round at least once
+pub fn default_horizon(scene: &Scene) -> f64 {
+    scene
+        .nodes
+        .iter()
+        .filter(|node| matches!(node.body, Body::Planet(_)))
+        .filter_map(|node| node.orbit.as_ref())
+        .map(|orbit| orbit.period())
+        .filter(|period| period.is_finite())
+        .fold(0.0, f64::max)
+        .min(MAX_HORIZON)
+}
+
+fn event(scene: &Scene, pair: EclipsePair, start: f64, end: f64) -> EclipseEvent {
+    // Golden-section search for where the target is deepest in the shadow
+    let ratio = (5.0_f64.sqrt() - 1.0) * 0.5;
+    let (mut low, mut high) = (start, end);
+    for _ in 0..REFINE_STEPS {
+        let left = high - (high - low) * ratio;
+        let right = low + (high - low) * ratio;
+        if pair.depth_at(scene, left) < pair.depth_at(scene, right) {
+            high = right;
+        } else {
+            low = left;
+        }
+    }
+    let peak = (low + high) * 0.5;
+
+    EclipseEvent {
+        pair,
+        kind: pair.classify_at(scene, peak).unwrap_or(EclipseKind::Partial),
+        start,
+        peak,
+        end,
+    }
+}
+
+// First time in (before, after] where `test` holds, given it fails at `before`
+fn refine(mut before: f64, mut after: f64, test: &dyn Fn(f64) -> bool) -> f64 {
+    for _ in 0..REFINE_STEPS {
+        let middle = (before + after) * 0.5;
+        if test(middle) {
+            after = middle;
+        } else {
+            before = middle;
+        }
+    }
+    after
+}
+
+// Reports eclipses as they begin and end while the simulation runs
+pub struct EclipseMonitor {
+    pairs: Vec<EclipsePair>,
+    active: Vec<Option<EclipseKind>>,
+}
+
+impl EclipseMonitor {
+    pub fn new(scene: &Scene) -> Self {
+        let pairs = pairs(scene);
+        let active = pairs.iter().map(|pair| pair.classify_now(scene)).collect();
+        EclipseMonitor { pairs, active }
+    }
+
+    // Messages for everything that started or ended since the last call
+    pub fn update(&mut self, scene: &Scene) -> Vec<String> {
+        let mut messages = Vec::new();
+        for (pair, active) in self.pairs.iter().zip(self.active.iter_mut()) {
+            let kind = pair.classify_now(scene);
+            if kind.is_some() == active.is_some() {
+                continue;
+            }
+            match (*active, kind) {
+                (None, Some(kind)) => messages.push(format!("{} begins", pair.describe(scene, kind))),
+                (Some(kind), None) => messages.push(format!("{} ends", pair.describe(scene, kind))),
+                _ => {}
+            }
+            *active = kind;
+        }
+        messages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A star of radius 1.5 at the origin and a planet of radius 0.5 ten units
+    // out; its umbra narrows to a point 5 units behind it
+    fn planet_shadow() -> Shadow {
+        Shadow::new(DVec3::zeros(), 1.5, DVec3::new(10.0, 0.0, 0.0), 0.5)
+    }
+
+    #[test]
+    fn a_moon_on_the_axis_inside_the_umbra_is_totally_eclipsed() {
+        assert_eq!(planet_shadow().classify(DVec3::new(11.0, 0.0, 0.0), 0.1), Some(EclipseKind::Total));
+    }
+
+    #[test]
+    fn a_moon_past_the_umbra_tip_sees_an_annular_eclipse() {
+        assert_eq!(planet_shadow().classify(DVec3::new(18.0, 0.0, 0.0), 0.1), Some(EclipseKind::Annular));
+    }
+
+    #[test]
+    fn a_moon_off_the_axis_only_reaches_the_penumbra() {
+        // The umbra is 0.4 wide here and the penumbra 0.7
+        assert_eq!(planet_shadow().classify(DVec3::new(11.0, 0.6, 0.0), 0.1), Some(EclipseKind::Partial));
+        assert_eq!(planet_shadow().classify(DVec3::new(11.0, 0.9, 0.0), 0.1), None);
+    }
+
+    #[test]
+    fn nothing_in_front_of_the_caster_is_shaded() {
+        assert_eq!(planet_shadow().classify(DVec3::new(9.0, 0.0, 0.0), 0.1), None);
+    }
+
+    #[test]
+    fn a_small_moon_crossing_the_star_is_a_transit() {
+        // The moon covers a third of the star's width, seen from the planet
+        let moon = Shadow::new(DVec3::zeros(), 1.5, DVec3::new(9.0, 0.0, 0.0), 0.05);
+        assert_eq!(moon.classify(DVec3::new(10.0, 0.0, 0.0), 0.5), Some(EclipseKind::Transit));
+    }
+}
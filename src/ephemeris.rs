@@ -0,0 +1,467 @@
+use crate::celestial::{Comet, OrbitalElements, Planet, PlanetPalette, PlanetShader, Ring, Spin, Star, name_seed};
+use crate::color::Color;
+use crate::scene::{Body, Scene};
+use nalgebra_glm::Vec3;
+
+const KM_PER_AU: f64 = 149_597_870.7;
+const SUN_RADIUS_KM: f64 = 695_700.0;
+const SECONDS_PER_DAY: f64 = 86_400.0;
+const DAYS_PER_CENTURY: f64 = 36_525.0;
+const J2000_JULIAN_DAY: f64 = 2_451_545.0;
+
+// Inner edge of the C ring to the outer edge of the A ring
+const SATURN_RINGS_KM: (f64, f64) = (74_658.0, 136_775.0);
+
+// Roughly where water ice starts to sublimate
+const COMET_ACTIVE_AU: f64 = 4.0;
+
+// Mean elements at J2000 relative to the ecliptic, from JPL's "Approximate
+// Positions of the Planets" (Standish), good to a fraction of a degree
+// between 1800 and 2050. The Earth entry is the Earth-Moon barycenter.
+struct PlanetData {
+    name: &'static str,
+    shader: PlanetShader,
+    palette: Option<PlanetPalette>,
+    radius_km: f64,
+    // Fraction of a solar mass
+    mass: f64,
+    // Sidereal day in hours
+    rotation_period: f64,
+    axial_tilt: f64,
+    semi_major_axis_au: f64,
+    eccentricity: f64,
+    inclination: f64,
+    mean_longitude: f64,
+    perihelion_longitude: f64,
+    ascending_node: f64,
+    // Degrees per Julian century
+    mean_longitude_rate: f64,
+}
+
+// Moon elements are approximate and referred to the ecliptic as well; the
+// mean anomaly at J2000 is only tracked for the Earth's Moon.
+struct MoonData {
+    name: &'static str,
+    parent: &'static str,
+    shader: PlanetShader,
+    palette: Option<PlanetPalette>,
+    radius_km: f64,
+    mass: f64,
+    axial_tilt: f64,
+    semi_major_axis_km: f64,
+    eccentricity: f64,
+    inclination: f64,
+    ascending_node: f64,
+    periapsis: f64,
+    mean_anomaly: f64,
+    // Sidereal orbital period in days
+    period: f64,
+}
+
+const PLANETS: [PlanetData; 8] = [
+    PlanetData {
+        name: "Mercury", shader: PlanetShader::Cratered, palette: None,
+        radius_km: 2_439.7, mass: 1.660e-7, rotation_period: 1_407.6, axial_tilt: 0.03,
+        semi_major_axis_au: 0.387_099_27, eccentricity: 0.205_635_93, inclination: 7.004_979_02,
+        mean_longitude: 252.250_323_50, perihelion_longitude: 77.457_796_28, ascending_node: 48.330_765_93,
+        mean_longitude_rate: 149_472.674_111_75,
+    },
+    PlanetData {
+        name: "Venus", shader: PlanetShader::Gaseous,
+        palette: Some(PlanetPalette::new(
+            Color::new(225, 195, 135), Color::new(245, 225, 170), Color::new(255, 240, 205), Color::new(215, 185, 130),
+        )),
+        radius_km: 6_051.8, mass: 2.448e-6, rotation_period: 5_832.5, axial_tilt: 177.4,
+        semi_major_axis_au: 0.723_335_66, eccentricity: 0.006_776_72, inclination: 3.394_676_05,
+        mean_longitude: 181.979_099_50, perihelion_longitude: 131.602_467_18, ascending_node: 76.679_842_55,
+        mean_longitude_rate: 58_517.815_387_29,
+    },
+    PlanetData {
+        name: "Earth", shader: PlanetShader::Terrestrial, palette: None,
+        radius_km: 6_371.0, mass: 3.003e-6, rotation_period: 23.934, axial_tilt: 23.44,
+        semi_major_axis_au: 1.000_002_61, eccentricity: 0.016_711_23, inclination: -0.000_015_31,
+        mean_longitude: 100.464_571_66, perihelion_longitude: 102.937_681_93, ascending_node: 0.0,
+        mean_longitude_rate: 35_999.372_449_81,
+    },
+    PlanetData {
+        name: "Mars", shader: PlanetShader::Desert,
+        palette: Some(PlanetPalette::new(
+            Color::new(140, 55, 30), Color::new(190, 95, 55), Color::new(105, 50, 35), Color::new(240, 235, 230),
+        )),
+        radius_km: 3_389.5, mass: 3.227e-7, rotation_period: 24.623, axial_tilt: 25.19,
+        semi_major_axis_au: 1.523_710_34, eccentricity: 0.093_394_10, inclination: 1.849_691_42,
+        mean_longitude: -4.553_432_05, perihelion_longitude: -23.943_629_59, ascending_node: 49.559_538_91,
+        mean_longitude_rate: 19_140.302_684_99,
+    },
+    PlanetData {
+        name: "Jupiter", shader: PlanetShader::Gaseous,
+        palette: Some(PlanetPalette::new(
+            Color::new(190, 150, 110), Color::new(235, 215, 180), Color::new(190, 95, 60), Color::new(150, 125, 100),
+        )),
+        radius_km: 69_911.0, mass: 9.545e-4, rotation_period: 9.925, axial_tilt: 3.13,
+        semi_major_axis_au: 5.202_887_00, eccentricity: 0.048_386_24, inclination: 1.304_396_95,
+        mean_longitude: 34.396_440_51, perihelion_longitude: 14.728_479_83, ascending_node: 100.473_909_09,
+        mean_longitude_rate: 3_034.746_127_75,
+    },
+    PlanetData {
+        name: "Saturn", shader: PlanetShader::Gaseous,
+        palette: Some(PlanetPalette::new(
+            Color::new(205, 180, 125), Color::new(235, 220, 170), Color::new(240, 205, 145), Color::new(175, 160, 120),
+        )),
+        radius_km: 58_232.0, mass: 2.858e-4, rotation_period: 10.656, axial_tilt: 26.73,
+        semi_major_axis_au: 9.536_675_94, eccentricity: 0.053_861_79, inclination: 2.485_991_87,
+        mean_longitude: 49.954_244_23, perihelion_longitude: 92.598_878_31, ascending_node: 113.662_424_48,
+        mean_longitude_rate: 1_222.493_622_01,
+    },
+    PlanetData {
+        name: "Uranus", shader: PlanetShader::Gaseous,
+        palette: Some(PlanetPalette::new(
+            Color::new(150, 205, 215), Color::new(170, 225, 230), Color::new(205, 240, 245), Color::new(130, 190, 205),
+        )),
+        radius_km: 25_362.0, mass: 4.366e-5, rotation_period: 17.24, axial_tilt: 97.77,
+        semi_major_axis_au: 19.189_164_64, eccentricity: 0.047_257_44, inclination: 0.772_637_83,
+        mean_longitude: 313.238_104_51, perihelion_longitude: 170.954_276_30, ascending_node: 74.016_925_03,
+        mean_longitude_rate: 428.482_027_85,
+    },
+    PlanetData {
+        name: "Neptune", shader: PlanetShader::Gaseous,
+        palette: Some(PlanetPalette::new(
+            Color::new(45, 85, 195), Color::new(70, 120, 220), Color::new(230, 240, 255), Color::new(40, 70, 170),
+        )),
+        radius_km: 24_622.0, mass: 5.151e-5, rotation_period: 16.11, axial_tilt: 28.32,
+        semi_major_axis_au: 30.069_922_76, eccentricity: 0.008_590_48, inclination: 1.770_043_47,
+        mean_longitude: -55.120_029_69, perihelion_longitude: 44.964_762_27, ascending_node: 131.784_225_74,
+        mean_longitude_rate: 218.459_453_25,
+    },
+];
+
+const MOONS: [MoonData; 7] = [
+    MoonData {
+        name: "Moon", parent: "Earth", shader: PlanetShader::Cratered, palette: None,
+        radius_km: 1_737.4, mass: 3.694e-8, axial_tilt: 6.68,
+        semi_major_axis_km: 384_400.0, eccentricity: 0.0549, inclination: 5.145,
+        ascending_node: 125.08, periapsis: 318.15, mean_anomaly: 134.96, period: 27.321_661,
+    },
+    MoonData {
+        name: "Io", parent: "Jupiter", shader: PlanetShader::Lava,
+        palette: Some(PlanetPalette::new(
+            Color::new(200, 180, 70), Color::new(255, 140, 0), Color::new(255, 230, 120), Color::new(200, 180, 70),
+        )),
+        radius_km: 1_821.6, mass: 4.491e-8, axial_tilt: 0.0,
+        semi_major_axis_km: 421_700.0, eccentricity: 0.0041, inclination: 2.2,
+        ascending_node: 0.0, periapsis: 0.0, mean_anomaly: 0.0, period: 1.769_138,
+    },
+    MoonData {
+        name: "Europa", parent: "Jupiter", shader: PlanetShader::Ice, palette: None,
+        radius_km: 1_560.8, mass: 2.413e-8, axial_tilt: 0.1,
+        semi_major_axis_km: 671_034.0, eccentricity: 0.009, inclination: 1.8,
+        ascending_node: 0.0, periapsis: 0.0, mean_anomaly: 90.0, period: 3.551_181,
+    },
+    MoonData {
+        name: "Ganymede", parent: "Jupiter", shader: PlanetShader::Cratered,
+        palette: Some(PlanetPalette::new(
+            Color::new(80, 70, 60), Color::new(150, 135, 115), Color::new(210, 205, 195), Color::new(150, 135, 115),
+        )),
+        radius_km: 2_634.1, mass: 7.452e-8, axial_tilt: 0.33,
+        semi_major_axis_km: 1_070_412.0, eccentricity: 0.0013, inclination: 2.2,
+        ascending_node: 0.0, periapsis: 0.0, mean_anomaly: 200.0, period: 7.154_553,
+    },
+    MoonData {
+        name: "Callisto", parent: "Jupiter", shader: PlanetShader::Cratered,
+        palette: Some(PlanetPalette::new(
+            Color::new(40, 35, 30), Color::new(90, 80, 70), Color::new(170, 165, 155), Color::new(90, 80, 70),
+        )),
+        radius_km: 2_410.3, mass: 5.409e-8, axial_tilt: 0.0,
+        semi_major_axis_km: 1_882_709.0, eccentricity: 0.0074, inclination: 2.0,
+        ascending_node: 0.0, periapsis: 0.0, mean_anomaly: 300.0, period: 16.689_018,
+    },
+    MoonData {
+        name: "Titan", parent: "Saturn", shader: PlanetShader::Gaseous,
+        palette: Some(PlanetPalette::new(
+            Color::new(195, 135, 55), Color::new(215, 160, 80), Color::new(230, 180, 100), Color::new(180, 120, 50),
+        )),
+        radius_km: 2_574.7, mass: 6.763e-8, axial_tilt: 0.3,
+        semi_major_axis_km: 1_221_870.0, eccentricity: 0.0288, inclination: 27.0,
+        ascending_node: 169.5, periapsis: 0.0, mean_anomaly: 0.0, period: 15.945_421,
+    },
+    MoonData {
+        name: "Triton", parent: "Neptune", shader: PlanetShader::Ice,
+        palette: Some(PlanetPalette::new(
+            Color::new(150, 120, 120), Color::new(225, 200, 195), Color::new(245, 235, 230), Color::WHITE,
+        )),
+        radius_km: 1_353.4, mass: 1.080e-8, axial_tilt: 0.0,
+        semi_major_axis_km: 354_759.0, eccentricity: 0.000_016, inclination: 130.0,
+        ascending_node: 177.6, periapsis: 0.0, mean_anomaly: 0.0, period: 5.876_854,
+    },
+];
+
+// Osculating elements near each comet's last perihelion passage, ecliptic J2000
+struct CometData {
+    name: &'static str,
+    radius_km: f64,
+    perihelion_au: f64,
+    eccentricity: f64,
+    inclination: f64,
+    ascending_node: f64,
+    periapsis: f64,
+    perihelion_julian_day: f64,
+    // Orbital period in years
+    period: f64,
+}
+
+const COMETS: [CometData; 2] = [
+    CometData {
+        name: "Halley", radius_km: 5.5, perihelion_au: 0.586, eccentricity: 0.967,
+        inclination: 162.26, ascending_node: 58.42, periapsis: 111.33,
+        perihelion_julian_day: 2_446_470.96, period: 75.32,
+    },
+    CometData {
+        name: "Encke", radius_km: 2.4, perihelion_au: 0.339, eccentricity: 0.848,
+        inclination: 11.35, ascending_node: 334.2, periapsis: 187.0,
+        perihelion_julian_day: 2_460_240.5, period: 3.3,
+    },
+];
+
+// Maps real sizes and distances to scene units. Each quantity goes through
+// `scale * value^exponent`: an exponent of 1 keeps true proportions, smaller
+// ones squeeze the huge range of sizes and distances so everything fits on screen.
+pub struct ScaleCompression {
+    // Scene units per AU^exponent
+    pub distance_scale: f64,
+    pub distance_exponent: f64,
+    // Scene units per km^exponent
+    pub radius_scale: f64,
+    pub radius_exponent: f64,
+    // Moons sit at `moon_distance_scale * (a / parent radius)^exponent` parent radii
+    pub moon_distance_scale: f64,
+    pub moon_distance_exponent: f64,
+}
+
+impl ScaleCompression {
+    pub fn true_scale() -> Self {
+        let units_per_au = 50.0;
+        ScaleCompression {
+            distance_scale: units_per_au,
+            distance_exponent: 1.0,
+            radius_scale: units_per_au / KM_PER_AU,
+            radius_exponent: 1.0,
+            moon_distance_scale: 1.0,
+            moon_distance_exponent: 1.0,
+        }
+    }
+
+    // Neptune lands around 40 units out and the Sun at about twice the Earth's radius
+    pub fn compressed() -> Self {
+        ScaleCompression {
+            distance_scale: 6.6,
+            distance_exponent: 0.53,
+            radius_scale: 0.0186,
+            radius_exponent: 0.35,
+            moon_distance_scale: 0.7,
+            moon_distance_exponent: 0.5,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "true" => Ok(ScaleCompression::true_scale()),
+            "compressed" => Ok(ScaleCompression::compressed()),
+            other => Err(format!("Unknown scale '{}', expected 'true' or 'compressed'", other)),
+        }
+    }
+
+    fn distance(&self, au: f64) -> f64 {
+        self.distance_scale * au.powf(self.distance_exponent)
+    }
+
+    fn radius(&self, km: f64) -> f32 {
+        (self.radius_scale * km.powf(self.radius_exponent)) as f32
+    }
+
+    fn moon_distance(&self, km: f64, parent_radius_km: f64) -> f64 {
+        let parent_radius = self.radius(parent_radius_km) as f64;
+        parent_radius * self.moon_distance_scale * (km / parent_radius_km).powf(self.moon_distance_exponent)
+    }
+}
+
+fn rotation_spin(axial_tilt: f64, rotation_period_hours: f64) -> Spin {
+    Spin::new(axial_tilt as f32, (rotation_period_hours * 3_600.0) as f32)
+}
+
+// The Sun, the eight planets, their major moons and two periodic comets, with
+// scene time counted in seconds from the J2000 epoch. Orbital periods are the
+// real ones, so at 1x the system barely moves; use the time warp to watch it.
+// Because distances are compressed but periods are not, the orbits don't
+// follow from the masses, so the viewer keeps the n-body mode off for it.
+pub fn solar_system(scale: &ScaleCompression) -> Scene {
+    let mut scene = Scene::new();
+
+    let mut sun = Star::new(scale.radius(SUN_RADIUS_KM), Vec3::zeros());
+    sun.temperature = 5_772.0;
+    sun.spin = rotation_spin(7.25, 609.12);
+    let sun_mass = sun.mass;
+    let sun_id = scene.add("Sun", Body::Star(sun), None, None).expect("the Sun has no parent");
+
+    for data in PLANETS.iter() {
+        let mut planet = Planet::new(data.shader, scale.radius(data.radius_km), name_seed(data.name));
+        if let Some(palette) = data.palette {
+            planet.palette = palette;
+        }
+        planet.spin = rotation_spin(data.axial_tilt, data.rotation_period);
+        planet.mass = data.mass * sun_mass;
+        if data.name == "Saturn" {
+            // Ring radii follow the planet's displayed size, whatever the compression
+            let per_km = planet.radius as f64 / data.radius_km;
+            let (inner, outer) = SATURN_RINGS_KM;
+            planet.ring = Some(Ring::new((inner * per_km) as f32, (outer * per_km) as f32, Color::new(210, 195, 160)));
+        }
+
+        let mut orbit = OrbitalElements::new(
+            scale.distance(data.semi_major_axis_au),
+            data.eccentricity,
+            data.inclination,
+            data.ascending_node,
+            data.perihelion_longitude - data.ascending_node,
+            data.mean_longitude - data.perihelion_longitude,
+        );
+        orbit.mean_motion = data.mean_longitude_rate.to_radians() / (DAYS_PER_CENTURY * SECONDS_PER_DAY);
+
+        scene.add(data.name, Body::Planet(planet), Some(sun_id), Some(orbit)).expect("the Sun is already in");
+    }
+
+    for data in MOONS.iter() {
+        let Some(parent) = PLANETS.iter().find(|p| p.name == data.parent) else {
+            continue;
+        };
+
+        let mut moon = Planet::new(data.shader, scale.radius(data.radius_km), name_seed(data.name));
+        if let Some(palette) = data.palette {
+            moon.palette = palette;
+        }
+        moon.mass = data.mass * sun_mass;
+
+        let mut orbit = OrbitalElements::new(
+            scale.moon_distance(data.semi_major_axis_km, parent.radius_km),
+            data.eccentricity,
+            data.inclination,
+            data.ascending_node,
+            data.periapsis,
+            data.mean_anomaly,
+        );
+        orbit.mean_motion = std::f64::consts::TAU / (data.period * SECONDS_PER_DAY);
+
+        moon.spin = Spin::locked(data.axial_tilt as f32, &orbit);
+
+        let parent_id = scene.find(parent.name).expect("planets are added before their moons");
+        scene.add(data.name, Body::Planet(moon), Some(parent_id), Some(orbit)).expect("the parent is already in");
+    }
+
+    for data in COMETS.iter() {
+        // Compressing the semi-major axis alone would drop the perihelion into
+        // the Sun, so both ends of the orbit are scaled and the shape follows
+        let aphelion_au = data.perihelion_au * (1.0 + data.eccentricity) / (1.0 - data.eccentricity);
+        let perihelion = scale.distance(data.perihelion_au);
+        let aphelion = scale.distance(aphelion_au);
+
+        let period = data.period * 365.25 * SECONDS_PER_DAY;
+        let since_perihelion = (J2000_JULIAN_DAY - data.perihelion_julian_day) * SECONDS_PER_DAY;
+        let mut orbit = OrbitalElements::new(
+            (perihelion + aphelion) * 0.5,
+            (aphelion - perihelion) / (aphelion + perihelion),
+            data.inclination,
+            data.ascending_node,
+            data.periapsis,
+            360.0 * since_perihelion / period,
+        );
+        orbit.mean_motion = std::f64::consts::TAU / period;
+
+        let comet = Comet::new(scale.radius(data.radius_km), (data.radius_km * 1000.0) as u32, scale.distance(COMET_ACTIVE_AU));
+        scene.add(data.name, Body::Comet(comet), Some(sun_id), Some(orbit)).expect("the Sun is already in");
+    }
+
+    scene
+}
+
+// Seconds from the J2000 epoch (2000-01-01 12:00) to midnight of a
+// "YYYY-MM-DD" Gregorian calendar date
+pub fn seconds_since_j2000(date: &str) -> Result<f64, String> {
+    let parts: Vec<&str> = date.split('-').collect();
+    let parse = |part: Option<&&str>| part.and_then(|p| p.parse::<i64>().ok());
+
+    let (Some(year), Some(month), Some(day)) = (parse(parts.first()), parse(parts.get(1)), parse(parts.get(2))) else {
+        return Err(format!("Invalid date '{}', expected YYYY-MM-DD", date));
+    };
+    if parts.len() != 3 || !(1..=12).contains(&month) {
+        return Err(format!("Invalid date '{}', expected YYYY-MM-DD", date));
+    }
+    if !(1..=days_in_month(year, month)).contains(&day) {
+        return Err(format!("Invalid date '{}', month {} has {} days", date, month, days_in_month(year, month)));
+    }
+
+    Ok((julian_day(year, month, day) - J2000_JULIAN_DAY) * SECONDS_PER_DAY)
+}
+
+// Inverse of `seconds_since_j2000`, as "YYYY-MM-DD HH:MM" (Meeus, ch. 7)
+pub fn format_date(seconds: f64) -> String {
+    let julian = seconds / SECONDS_PER_DAY + J2000_JULIAN_DAY + 0.5;
+    let z = julian.floor();
+    let fraction = julian - z;
+    let a = if z < 2_299_161.0 {
+        z
+    } else {
+        let alpha = ((z - 1_867_216.25) / 36_524.25).floor();
+        z + 1.0 + alpha - (alpha / 4.0).floor()
+    };
+    let b = a + 1524.0;
+    let c = ((b - 122.1) / 365.25).floor();
+    let d = (365.25 * c).floor();
+    let e = ((b - d) / 30.6001).floor();
+
+    let day = b - d - (30.6001 * e).floor();
+    let month = if e < 14.0 { e - 1.0 } else { e - 13.0 };
+    let year = if month > 2.0 { c - 4716.0 } else { c - 4715.0 };
+    let minutes = (fraction * 1440.0).floor() as i64;
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}", year as i64, month as i64, day as i64, minutes / 60, minutes % 60)
+}
+
+// Gregorian calendar, as `julian_day` assumes
+fn days_in_month(year: i64, month: i64) -> i64 {
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Julian day at 0h UT (Meeus, Astronomical Algorithms, ch. 7)
+fn julian_day(year: i64, month: i64, day: i64) -> f64 {
+    let (y, m) = if month <= 2 { (year - 1, month + 12) } else { (year, month) };
+    let a = y.div_euclid(100);
+    let b = 2 - a + a.div_euclid(4);
+
+    (365.25 * (y as f64 + 4716.0)).floor() + (30.6001 * (m as f64 + 1.0)).floor() + day as f64 + b as f64 - 1524.5
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dates_round_trip_through_format_date() {
+        for date in ["2000-01-01", "1999-12-31", "2024-02-29", "2023-02-28", "2100-03-01", "1969-07-20", "2061-07-28"] {
+            let seconds = seconds_since_j2000(date).unwrap();
+            assert_eq!(format_date(seconds), format!("{} 00:00", date));
+        }
+    }
+
+    #[test]
+    fn rejects_days_past_the_end_of_the_month() {
+        for date in ["2024-02-30", "2023-02-29", "1900-02-29", "2024-04-31", "2024-13-01", "2024-01-00"] {
+            assert!(seconds_since_j2000(date).is_err(), "{} was accepted", date);
+        }
+        assert!(seconds_since_j2000("2000-02-29").is_ok());
+    }
+}
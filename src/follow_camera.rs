@@ -0,0 +1,64 @@
+use crate::camera::Camera;
+use crate::celestial::Ship;
+
+// Behind and above the ship, in scene units
+const CHASE_DISTANCE: f32 = 2.0;
+const CHASE_HEIGHT: f32 = 0.5;
+// How quickly the chase view swings round after the ship turns, per second
+const CHASE_STIFFNESS: f32 = 6.0;
+// Ahead of the ship's origin, clear of the hull
+const COCKPIT_OFFSET: f32 = 0.05;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraMode {
+    // Trails the ship, lagging a little behind its turns
+    Chase,
+    // Looks out of the ship's nose; the ship itself isn't drawn
+    Cockpit,
+    // Detached: the camera flies on its own and the ship drifts on
+    Free,
+}
+
+impl CameraMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            CameraMode::Chase => "chase",
+            CameraMode::Cockpit => "cockpit",
+            CameraMode::Free => "free",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            CameraMode::Chase => CameraMode::Cockpit,
+            CameraMode::Cockpit => CameraMode::Free,
+            CameraMode::Free => CameraMode::Chase,
+        }
+    }
+
+    // Places the camera relative to the ship for this mode
+    pub fn follow(self, ship: &Ship, camera: &mut Camera, delta_time: f32) {
+        match self {
+            CameraMode::Chase => {
+                let mut target = ship.orientation;
+                // q and -q are the same rotation; take the short way round
+                if camera.orientation.dot(&target) < 0.0 {
+                    target = -target;
+                }
+                let t = 1.0 - (-CHASE_STIFFNESS * delta_time).exp();
+                camera.orientation = nalgebra_glm::quat_normalize(&nalgebra_glm::quat_slerp(&camera.orientation, &target, t));
+                camera.eye = ship.position - camera.forward() * CHASE_DISTANCE + camera.up() * CHASE_HEIGHT;
+            }
+            CameraMode::Cockpit => {
+                camera.orientation = ship.orientation;
+                camera.eye = ship.position + ship.forward() * COCKPIT_OFFSET;
+            }
+            CameraMode::Free => {}
+        }
+    }
+}
+
+// Where to put a ship so the chase view starts from the camera as it is
+pub fn launch_point(camera: &Camera) -> nalgebra_glm::Vec3 {
+    camera.eye + camera.forward() * CHASE_DISTANCE - camera.up() * CHASE_HEIGHT
+}
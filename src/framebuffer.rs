@@ -19,6 +19,15 @@ impl Framebuffer {
         }
     }
 
+    pub fn save_png(&self, path: &str) -> Result<(), String> {
+        let mut pixels = Vec::with_capacity(self.buffer.len() * 3);
+        for &pixel in &self.buffer {
+            pixels.extend_from_slice(&[(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]);
+        }
+        image::save_buffer(path, &pixels, self.width as u32, self.height as u32, image::ColorType::Rgb8)
+            .map_err(|e| format!("Cannot write {}: {}", path, e))
+    }
+
     pub fn clear(&mut self) {
         let bg_hex = self.background_color.to_hex();
         for pixel in self.buffer.iter_mut() {
@@ -29,24 +38,59 @@ impl Framebuffer {
         }
     }
 
-    pub fn point(&mut self, x: usize, y: usize, color: &Color) {
+    pub fn point_with_depth(&mut self, x: usize, y: usize, depth: f32, color: &Color) {
         if x < self.width && y < self.height {
             let index = y * self.width + x;
-            self.buffer[index] = color.to_hex();
+            if depth < self.zbuffer[index] {
+                self.zbuffer[index] = depth;
+                self.buffer[index] = color.to_hex();
+            }
         }
     }
 
-    pub fn point_with_depth(&mut self, x: usize, y: usize, depth: f32, color: &Color) {
+    // Additive blend for glows and sprites; depth is tested but never written
+    pub fn blend_add(&mut self, x: usize, y: usize, depth: f32, color: &Color) {
+        if x < self.width && y < self.height {
+            let index = y * self.width + x;
+            if depth < self.zbuffer[index] {
+                let blended = Color::from_hex(self.buffer[index]) + *color;
+                self.buffer[index] = blended.to_hex();
+            }
+        }
+    }
+
+    // Alpha blend for translucent surfaces; only mostly opaque fragments write depth
+    pub fn blend_alpha(&mut self, x: usize, y: usize, depth: f32, color: &Color, alpha: f32) {
         if x < self.width && y < self.height {
             let index = y * self.width + x;
             if depth < self.zbuffer[index] {
+                let blended = Color::from_hex(self.buffer[index]).lerp(color, alpha);
+                self.buffer[index] = blended.to_hex();
+                if alpha > 0.5 {
+                    self.zbuffer[index] = depth;
+                }
+            }
+        }
+    }
+
+    // Alpha blend that always writes depth, for glowing translucent surfaces
+    // that later screen-space passes need to find
+    pub fn blend_alpha_solid(&mut self, x: usize, y: usize, depth: f32, color: &Color, alpha: f32) {
+        if x < self.width && y < self.height {
+            let index = y * self.width + x;
+            if depth < self.zbuffer[index] {
+                let blended = Color::from_hex(self.buffer[index]).lerp(color, alpha);
+                self.buffer[index] = blended.to_hex();
                 self.zbuffer[index] = depth;
-                self.buffer[index] = color.to_hex();
             }
         }
     }
 
-    pub fn set_background_color(&mut self, color: Color) {
-        self.background_color = color;
+    pub fn depth_at(&self, x: usize, y: usize) -> f32 {
+        if x < self.width && y < self.height {
+            self.zbuffer[y * self.width + x]
+        } else {
+            f32::INFINITY
+        }
     }
 }
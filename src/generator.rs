@@ -0,0 +1,375 @@
+use crate::camera::Camera;
+use crate::celestial::orbit::STAR_GM;
+use crate::celestial::star::StellarClass;
+use crate::celestial::{AsteroidBelt, Comet, OrbitalElements, Planet, PlanetPalette, PlanetShader, Ring, Spin, Star};
+use crate::color::Color;
+use crate::scene::{Body, HILL_STABLE_FRACTION, NodeId, Scene};
+use crate::scene_file::StarSystem;
+use nalgebra_glm::Vec3;
+use rand::Rng;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+// Where a planet around the default Sun (radius 1.5, 5800 K) gets Terra's climate
+const HABITABLE_DISTANCE: f64 = 8.0;
+const MAX_ORBIT: f64 = 60.0;
+// Heaviest a planet gets to hold its moons, as a share of the star's mass
+const MAX_PLANET_MASS: f64 = 0.02;
+// Gap between neighbours in Hill radii of each, enough that they barely
+// stir each other's orbits under n-body gravity
+const HILL_SPACING: f64 = 2.0;
+// Random orbits stay below this, so a moon never swings out past 1.1 times its semi-major axis
+const MAX_ECCENTRICITY: f64 = 0.1;
+
+const SYLLABLES: [&str; 16] = [
+    "ka", "ve", "lor", "tha", "mi", "zen", "or", "qua", "dra", "sel", "ny", "rho", "tes", "an", "vo", "ix",
+];
+
+const ROMAN: [&str; 6] = ["I", "II", "III", "IV", "V", "VI"];
+
+// Alternative gas giant palettes, so not every giant uses the shader's default colors
+const GIANT_PALETTES: [PlanetPalette; 2] = [
+    PlanetPalette::new(Color::new(200, 160, 110), Color::new(235, 215, 175), Color::new(190, 110, 70), Color::new(150, 120, 90)),
+    PlanetPalette::new(Color::new(60, 110, 200), Color::new(120, 180, 230), Color::new(220, 240, 255), Color::new(40, 70, 150)),
+];
+
+// Builds a whole system from one seed. ChaCha8 output is stable across
+// platforms and crate versions, so a seed always gives the same system.
+pub fn generate(seed: u64) -> StarSystem {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut scene = Scene::new();
+
+    let class = pick_class(&mut rng);
+    let mut star = Star::new(star_radius(class), Vec3::zeros());
+    star.temperature = class.temperature() * rng.gen_range(0.92..1.08);
+    star.mass = STAR_GM * star_mass(class);
+    star.seed = rng.gen();
+    star.spin = Spin::new(rng.gen_range(0.0..15.0), rng.gen_range(80.0..200.0));
+
+    // Luminosity relative to the default Sun sets the temperature zones
+    let luminosity = star.luminosity() as f64;
+    let habitable = HABITABLE_DISTANCE * luminosity.sqrt();
+    let star_radius = star.radius as f64;
+    let star_mass = star.mass;
+
+    let name = star_name(&mut rng);
+    let star_id = scene.add(&name, Body::Star(star), None, None).expect("the star has no parent");
+
+    // Titius-Bode-like spacing: each orbit a roughly constant factor further out
+    let planet_count = rng.gen_range(2..=9);
+    let mut distance = star_radius * rng.gen_range(2.5..3.5);
+    // Distance, radius and Hill radius per unit of distance of the last planet placed
+    let mut previous: Option<(f64, f32, f64)> = None;
+    let mut belt_placed = false;
+
+    for index in 0..planet_count {
+        let insolation = habitable / distance;
+        let shader = pick_shader(&mut rng, insolation);
+        let radius = planet_radius(&mut rng, shader);
+
+        let mut planet = Planet::new(shader, radius, rng.gen());
+        planet.spin = random_spin(&mut rng);
+        if shader == PlanetShader::Gaseous && rng.gen_bool(0.6) {
+            planet.palette = GIANT_PALETTES[rng.gen_range(0..GIANT_PALETTES.len())];
+        }
+
+        let mut moons_from = radius * 1.4;
+        if shader == PlanetShader::Gaseous && rng.gen_bool(0.35) {
+            let inner = radius * rng.gen_range(1.25..1.5);
+            let outer = radius * rng.gen_range(1.9..2.4);
+            let tint = rng.gen_range(160..230);
+            planet.ring = Some(Ring::new(inner, outer, Color::new(tint, tint - 15, tint - 40)));
+            moons_from = outer * 1.2;
+        }
+
+        // Moons only where the planet's Hill sphere can hold them, even made
+        // as heavy as a planet gets; it weighs whatever the outermost one needs
+        let moon_count = match shader {
+            PlanetShader::Gaseous => rng.gen_range(0..=4),
+            _ if radius > 0.5 => rng.gen_range(0..=1),
+            _ => 0,
+        };
+        let mut moons = moon_distances(&mut rng, moon_count, moons_from);
+        let holding_mass = |orbit: f64| {
+            let reach = orbit * (1.0 + MAX_ECCENTRICITY) / (HILL_STABLE_FRACTION * distance);
+            3.0 * star_mass * reach.powi(3)
+        };
+        moons.retain(|&orbit| holding_mass(orbit) <= MAX_PLANET_MASS * star_mass);
+        if let Some(&outermost) = moons.last() {
+            planet.mass = planet.mass.max(holding_mass(outermost));
+        }
+        let hill = (planet.mass / (3.0 * star_mass)).cbrt();
+
+        // Keep neighbours clear of each other on screen and their Hill spheres
+        // HILL_SPACING radii apart; further out the moons only sit more comfortably
+        if let Some((last_distance, last_radius, last_hill)) = previous {
+            distance = distance
+                .max(last_distance + 3.0 * (last_radius + radius) as f64)
+                .max(last_distance * (1.0 + HILL_SPACING * last_hill) / (1.0 - HILL_SPACING * hill));
+        }
+        if distance > MAX_ORBIT {
+            break;
+        }
+
+        // A belt of leftover rocks just inside the first giant, like the main belt
+        if !belt_placed && shader == PlanetShader::Gaseous {
+            if let Some((last_distance, _, _)) = previous {
+                if distance - last_distance > 4.0 {
+                    let center = (last_distance + distance) * 0.5;
+                    add_belt(&mut scene, &mut rng, star_id, &name, center, (distance - last_distance) * 0.15, star_mass);
+                    belt_placed = true;
+                }
+            }
+        }
+
+        let planet_name = format!("{} {}", name, (b'b' + index as u8) as char);
+        let orbit = random_orbit(&mut rng, distance, 4.0).around(star_mass);
+        let planet_id = scene.add(&planet_name, Body::Planet(planet), Some(star_id), Some(orbit)).expect("the star is already in");
+        add_moons(&mut scene, &mut rng, planet_id, &moons, insolation);
+
+        previous = Some((distance, radius, hill));
+        distance *= rng.gen_range(1.4..1.9);
+    }
+
+    // Drawn last, so the planets a seed gives never depend on it
+    if rng.gen_bool(0.7) {
+        add_comet(&mut scene, &mut rng, star_id, &name, star_radius, habitable);
+    }
+
+    let first_orbit = (star_radius * 6.0) as f32;
+    StarSystem {
+        scene,
+        camera: Camera::new(
+            Vec3::new(0.0, first_orbit * 0.5, -first_orbit * 2.5),
+            Vec3::zeros(),
+            Vec3::new(0.0, 1.0, 0.0),
+        ),
+        skybox_stars: 1000,
+    }
+}
+
+// Weighted towards the cooler, more common classes; hot O and B stars stay rare
+fn pick_class(rng: &mut ChaCha8Rng) -> StellarClass {
+    let roll: f32 = rng.gen();
+    match roll {
+        r if r < 0.02 => StellarClass::O,
+        r if r < 0.07 => StellarClass::B,
+        r if r < 0.17 => StellarClass::A,
+        r if r < 0.35 => StellarClass::F,
+        r if r < 0.60 => StellarClass::G,
+        r if r < 0.85 => StellarClass::K,
+        _ => StellarClass::M,
+    }
+}
+
+// Display radius; real ratios would make dwarfs invisible next to giants
+fn star_radius(class: StellarClass) -> f32 {
+    match class {
+        StellarClass::O => 2.4,
+        StellarClass::B => 2.0,
+        StellarClass::A => 1.7,
+        StellarClass::F => 1.6,
+        StellarClass::G => 1.5,
+        StellarClass::K => 1.3,
+        StellarClass::M => 1.0,
+    }
+}
+
+// Mass relative to the default star, compressed like the radius
+fn star_mass(class: StellarClass) -> f64 {
+    match class {
+        StellarClass::O => 3.0,
+        StellarClass::B => 2.2,
+        StellarClass::A => 1.6,
+        StellarClass::F => 1.2,
+        StellarClass::G => 1.0,
+        StellarClass::K => 0.8,
+        StellarClass::M => 0.5,
+    }
+}
+
+// Insolation is how much closer to the star than the habitable zone the orbit
+// is: above 1 is hotter than Terra, below 1 colder
+fn pick_shader(rng: &mut ChaCha8Rng, insolation: f64) -> PlanetShader {
+    let roll: f32 = rng.gen();
+    if insolation > 1.6 {
+        if roll < 0.5 { PlanetShader::Lava } else { PlanetShader::Cratered }
+    } else if insolation > 1.15 {
+        match roll {
+            r if r < 0.5 => PlanetShader::Desert,
+            r if r < 0.8 => PlanetShader::Rocky,
+            _ => PlanetShader::Cratered,
+        }
+    } else if insolation > 0.85 {
+        if roll < 0.6 { PlanetShader::Terrestrial } else { PlanetShader::Ocean }
+    } else if insolation > 0.55 {
+        match roll {
+            r if r < 0.6 => PlanetShader::Gaseous,
+            r if r < 0.8 => PlanetShader::Desert,
+            _ => PlanetShader::Ice,
+        }
+    } else if roll < 0.6 {
+        PlanetShader::Gaseous
+    } else {
+        PlanetShader::Ice
+    }
+}
+
+fn planet_radius(rng: &mut ChaCha8Rng, shader: PlanetShader) -> f32 {
+    match shader {
+        PlanetShader::Gaseous => rng.gen_range(1.0..1.8),
+        PlanetShader::Ice => rng.gen_range(0.6..1.0),
+        _ => rng.gen_range(0.3..0.8),
+    }
+}
+
+fn random_orbit(rng: &mut ChaCha8Rng, semi_major_axis: f64, max_inclination: f64) -> OrbitalElements {
+    OrbitalElements::new(
+        semi_major_axis,
+        rng.gen_range(0.0..MAX_ECCENTRICITY),
+        rng.gen_range(0.0..max_inclination),
+        rng.gen_range(0.0..360.0),
+        rng.gen_range(0.0..360.0),
+        rng.gen_range(0.0..360.0),
+    )
+}
+
+// Mostly modest tilts; now and then a planet knocked on its side or spinning backwards
+fn random_spin(rng: &mut ChaCha8Rng) -> Spin {
+    let tilt = if rng.gen_bool(0.1) { rng.gen_range(60.0..100.0) } else { rng.gen_range(0.0..35.0) };
+    let period = rng.gen_range(15.0..120.0);
+    let direction = if rng.gen_bool(0.15) { -1.0 } else { 1.0 };
+    Spin::new(tilt, period * direction)
+}
+
+// Semi-major axes of up to `count` moons, innermost first
+fn moon_distances(rng: &mut ChaCha8Rng, count: usize, first_orbit: f32) -> Vec<f64> {
+    let mut distance = first_orbit as f64 + rng.gen_range(0.2..0.5);
+    (0..count.min(ROMAN.len()))
+        .map(|_| {
+            let orbit = distance;
+            distance *= rng.gen_range(1.3..1.7);
+            orbit
+        })
+        .collect()
+}
+
+fn add_moons(scene: &mut Scene, rng: &mut ChaCha8Rng, planet_id: NodeId, distances: &[f64], insolation: f64) {
+    let planet_name = scene.nodes[planet_id].name.clone();
+    let planet_mass = scene.mass(Some(planet_id));
+
+    for (index, (&distance, numeral)) in distances.iter().zip(ROMAN).enumerate() {
+        let shader = match rng.gen::<f32>() {
+            r if r < 0.15 && index == 0 => PlanetShader::Lava,
+            _ if insolation < 0.85 && rng.gen_bool(0.6) => PlanetShader::Ice,
+            _ => PlanetShader::Cratered,
+        };
+
+        let mut moon = Planet::new(shader, rng.gen_range(0.12..0.3), rng.gen());
+
+        let orbit = random_orbit(rng, distance, 10.0).around(planet_mass);
+        moon.spin = Spin::locked(rng.gen_range(0.0..5.0), &orbit);
+
+        scene
+            .add(&format!("{} {}", planet_name, numeral), Body::Planet(moon), Some(planet_id), Some(orbit))
+            .expect("the planet is already in");
+    }
+}
+
+fn add_belt(
+    scene: &mut Scene,
+    rng: &mut ChaCha8Rng,
+    star_id: NodeId,
+    star_name: &str,
+    center: f64,
+    half_width: f64,
+    star_mass: f64,
+) {
+    let belt = AsteroidBelt::new(
+        rng.gen_range(1500..=2500),
+        center - half_width,
+        center + half_width,
+        rng.gen_range(4.0..12.0),
+        rng.gen(),
+        star_mass,
+    );
+    scene.add(&format!("{} belt", star_name), Body::Belt(belt), Some(star_id), None).expect("the star is already in");
+}
+
+// A long, steep orbit that dives close to the star and comes alive near the habitable zone
+fn add_comet(scene: &mut Scene, rng: &mut ChaCha8Rng, star_id: NodeId, star_name: &str, star_radius: f64, habitable: f64) {
+    let perihelion = star_radius * rng.gen_range(2.5..5.0);
+    let aphelion = rng.gen_range(30.0..MAX_ORBIT);
+    let star_mass = scene.mass(Some(star_id));
+
+    let orbit = OrbitalElements::new(
+        (perihelion + aphelion) * 0.5,
+        (aphelion - perihelion) / (aphelion + perihelion),
+        rng.gen_range(0.0..180.0),
+        rng.gen_range(0.0..360.0),
+        rng.gen_range(0.0..360.0),
+        rng.gen_range(0.0..360.0),
+    )
+    .around(star_mass);
+
+    let mut comet = Comet::new(rng.gen_range(0.06..0.12), rng.gen(), habitable * 1.5);
+    comet.spin = Spin::new(rng.gen_range(0.0..90.0), rng.gen_range(5.0..30.0));
+    scene.add(&format!("{} comet", star_name), Body::Comet(comet), Some(star_id), Some(orbit)).expect("the star is already in");
+}
+
+fn star_name(rng: &mut ChaCha8Rng) -> String {
+    let syllables = rng.gen_range(2..=3);
+    let mut name: String = (0..syllables).map(|_| SYLLABLES[rng.gen_range(0..SYLLABLES.len())]).collect();
+    if let Some(first) = name.get_mut(0..1) {
+        first.make_ascii_uppercase();
+    }
+    name
+}
+
+// Seed for "a new system each run" when none was given
+pub fn random_seed() -> u64 {
+    rand::thread_rng().gen()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene_file::snapshot;
+
+    fn describe(seed: u64) -> String {
+        let system = generate(seed);
+        toml::to_string(&snapshot(&system.scene, &system.camera, system.skybox_stars)).unwrap()
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_system() {
+        for seed in [0, 1, 42, 7_777, u64::MAX] {
+            assert_eq!(describe(seed), describe(seed), "seed {}", seed);
+        }
+        assert_ne!(describe(1), describe(2));
+    }
+
+    #[test]
+    fn moons_stay_inside_their_planets_hill_spheres() {
+        let mut moons = 0;
+        for seed in 0..100 {
+            let scene = generate(seed).scene;
+            assert!(scene.loose_moons().is_empty(), "seed {}: {:?}", seed, scene.loose_moons());
+            for node in &scene.nodes {
+                let (Some(parent), Some(orbit)) = (node.parent, &node.orbit) else {
+                    continue;
+                };
+                if !matches!(scene.nodes[parent].body, Body::Planet(_)) {
+                    continue;
+                }
+                let hill = scene.hill_radius(parent).expect("planets have mass and an orbit");
+                let apoapsis = orbit.semi_major_axis * (1.0 + orbit.eccentricity);
+                assert!(apoapsis <= hill * HILL_STABLE_FRACTION, "seed {}: {} reaches {} of {}", seed, node.name, apoapsis, hill);
+                moons += 1;
+            }
+        }
+        // Enough systems to have met plenty of moons
+        assert!(moons > 20, "only {} moons", moons);
+    }
+}
@@ -36,34 +36,28 @@ pub fn load_obj(file_path: &str) -> Result<Mesh, String> {
         let parts: Vec<&str> = line.split_whitespace().collect();
         
         match parts[0] {
-            "v" => {
-                if parts.len() >= 4 {
-                    let x: f32 = parts[1].parse().unwrap_or(0.0);
-                    let y: f32 = parts[2].parse().unwrap_or(0.0);
-                    let z: f32 = parts[3].parse().unwrap_or(0.0);
-                    positions.push(Vec3::new(x, y, z));
-                }
+            "v" if parts.len() >= 4 => {
+                let x: f32 = parts[1].parse().unwrap_or(0.0);
+                let y: f32 = parts[2].parse().unwrap_or(0.0);
+                let z: f32 = parts[3].parse().unwrap_or(0.0);
+                positions.push(Vec3::new(x, y, z));
             }
-            "vn" => {
-                if parts.len() >= 4 {
-                    let x: f32 = parts[1].parse().unwrap_or(0.0);
-                    let y: f32 = parts[2].parse().unwrap_or(0.0);
-                    let z: f32 = parts[3].parse().unwrap_or(0.0);
-                    normals.push(Vec3::new(x, y, z));
-                }
+            "vn" if parts.len() >= 4 => {
+                let x: f32 = parts[1].parse().unwrap_or(0.0);
+                let y: f32 = parts[2].parse().unwrap_or(0.0);
+                let z: f32 = parts[3].parse().unwrap_or(0.0);
+                normals.push(Vec3::new(x, y, z));
             }
-            "vt" => {
-                if parts.len() >= 3 {
-                    let u: f32 = parts[1].parse().unwrap_or(0.0);
-                    let v: f32 = parts[2].parse().unwrap_or(0.0);
-                    tex_coords.push(Vec2::new(u, v));
-                }
+            "vt" if parts.len() >= 3 => {
+                let u: f32 = parts[1].parse().unwrap_or(0.0);
+                let v: f32 = parts[2].parse().unwrap_or(0.0);
+                tex_coords.push(Vec2::new(u, v));
             }
             "f" => {
                 let mut face_vertices: Vec<Vertex> = Vec::new();
                 
-                for i in 1..parts.len() {
-                    let indices: Vec<&str> = parts[i].split('/').collect();
+                for part in &parts[1..] {
+                    let indices: Vec<&str> = part.split('/').collect();
                     
                     let pos_idx: usize = indices[0].parse::<usize>().unwrap_or(1) - 1;
                     let tex_idx: Option<usize> = if indices.len() > 1 && !indices[1].is_empty() {
@@ -0,0 +1,33 @@
+use crate::vertex::Vertex;
+use crate::color::Color;
+use crate::geometry::Mesh;
+use nalgebra_glm::Vec3;
+use std::f32::consts::PI;
+
+// Flat annulus in the XZ plane, i.e. around a body's equator
+pub fn create_ring(inner_radius: f32, outer_radius: f32, segments: u32) -> Mesh {
+    let normal = Vec3::new(0.0, 1.0, 0.0);
+    let point = |radius: f32, segment: u32| {
+        let phi = segment as f32 * 2.0 * PI / segments as f32;
+        Vertex::new(Vec3::new(radius * phi.cos(), 0.0, radius * phi.sin()), normal, Color::WHITE)
+    };
+
+    let mut triangles = Vec::new();
+
+    for segment in 0..segments {
+        let inner = point(inner_radius, segment);
+        let outer = point(outer_radius, segment);
+        let next_inner = point(inner_radius, segment + 1);
+        let next_outer = point(outer_radius, segment + 1);
+
+        triangles.push(inner);
+        triangles.push(outer);
+        triangles.push(next_inner);
+
+        triangles.push(next_inner);
+        triangles.push(outer);
+        triangles.push(next_outer);
+    }
+
+    Mesh::new(triangles)
+}
@@ -0,0 +1,50 @@
+use crate::color::Color;
+use crate::geometry::{Mesh, create_sphere};
+use crate::shaders::noise::ShaderNoise;
+use nalgebra_glm::Vec3;
+
+// Lumpy rock about one unit across: a coarse sphere pushed in and out by noise
+// and squashed along two axes, with flat normals so the facets read as stone
+pub fn create_rock(seed: u32, segments: u32) -> Mesh {
+    let noise = ShaderNoise::new(seed);
+    let stretch = Vec3::new(
+        1.0,
+        0.55 + 0.4 * noise.cell_value(&Vec3::new(0.5, 0.5, 0.5)),
+        0.7 + 0.3 * noise.cell_value(&Vec3::new(7.5, 3.5, 1.5)),
+    );
+    let shade = 0.75 + 0.35 * noise.cell_value(&Vec3::new(2.5, 9.5, 4.5));
+    let base = Color::new(125, 115, 105) * shade;
+
+    let shape = |position: &Vec3| {
+        let direction = position.normalize();
+        let lumps = 1.0 + 0.3 * noise.fbm(&(direction * 1.7));
+        (direction * lumps).component_mul(&stretch)
+    };
+
+    let mut vertices = Vec::new();
+    for triangle in create_sphere(1.0, segments, segments / 2 + 1).vertices.chunks_exact(3) {
+        let a = shape(&triangle[0].position);
+        let b = shape(&triangle[1].position);
+        let c = shape(&triangle[2].position);
+
+        // The sphere's pole rows collapse into zero-area triangles
+        let cross = (b - a).cross(&(c - a));
+        if cross.norm() < 1e-6 {
+            continue;
+        }
+        let mut normal = cross.normalize();
+        if normal.dot(&(a + b + c)) < 0.0 {
+            normal = -normal;
+        }
+
+        for (vertex, position) in triangle.iter().zip([a, b, c]) {
+            let mut vertex = *vertex;
+            vertex.position = position;
+            vertex.normal = normal;
+            vertex.color = base * (0.85 + 0.3 * noise.perlin(&(position * 3.0)));
+            vertices.push(vertex);
+        }
+    }
+
+    Mesh::new(vertices)
+}
@@ -0,0 +1,133 @@
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::uniforms::Uniforms;
+use nalgebra_glm::{Vec2, Vec3};
+
+// Ghosts sit along the line from the light through the screen centre:
+// (position along the line, radius in px, tint)
+const GHOSTS: [(f32, f32, u32); 6] = [
+    (0.45, 18.0, 0x3a5cff),
+    (0.7, 8.0, 0xffb347),
+    (1.0, 30.0, 0x2ecc71),
+    (1.25, 12.0, 0xff5fa2),
+    (1.6, 44.0, 0x6a5acd),
+    (2.1, 20.0, 0xffd27f),
+];
+
+const SAMPLES: usize = 5;
+
+pub struct LensFlare {
+    pub intensity: f32,
+    visibility: f32,
+}
+
+impl LensFlare {
+    pub fn new() -> Self {
+        LensFlare {
+            intensity: 0.6,
+            visibility: 0.0,
+        }
+    }
+
+    // Fraction of the light's disk that is not hidden behind closer geometry,
+    // sampled against the z-buffer on a small grid
+    fn occlusion_test(center: &Vec3, radius_px: f32, front_depth: f32, framebuffer: &Framebuffer) -> f32 {
+        let mut visible = 0;
+        let mut total = 0;
+
+        for j in 0..SAMPLES {
+            for i in 0..SAMPLES {
+                let u = (i as f32 / (SAMPLES - 1) as f32) * 2.0 - 1.0;
+                let v = (j as f32 / (SAMPLES - 1) as f32) * 2.0 - 1.0;
+                if u * u + v * v > 1.0 {
+                    continue;
+                }
+                total += 1;
+
+                let x = center.x + u * radius_px * 0.9;
+                let y = center.y + v * radius_px * 0.9;
+                if x < 0.0 || y < 0.0 || x >= framebuffer.width as f32 || y >= framebuffer.height as f32 {
+                    continue;
+                }
+
+                // Anything nearer than the light's closest point is an occluder
+                if framebuffer.depth_at(x as usize, y as usize) >= front_depth - 1e-4 {
+                    visible += 1;
+                }
+            }
+        }
+
+        visible as f32 / total.max(1) as f32
+    }
+
+    // Must run after the rest of the scene so the z-buffer holds every occluder
+    pub fn render(&mut self, light_position: &Vec3, light_radius: f32, tint: Color, framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
+        let projected = uniforms.world_to_screen(light_position).and_then(|center| {
+            let edge_point = light_position + uniforms.camera_right() * light_radius;
+            uniforms
+                .world_to_screen(&edge_point)
+                .map(|edge| (center, (edge.xy() - center.xy()).magnitude()))
+        });
+
+        let towards_camera = (uniforms.camera_position - light_position).normalize();
+        let front = uniforms.world_to_screen(&(light_position + towards_camera * light_radius));
+
+        let target = match (projected, front) {
+            (Some((center, radius_px)), Some(front)) if (-1.0..=1.0).contains(&center.z) => {
+                Self::occlusion_test(&center, radius_px.max(1.0), front.z, framebuffer)
+            }
+            _ => 0.0,
+        };
+
+        // Ease towards the new visibility so the flare fades instead of popping
+        self.visibility += (target - self.visibility) * 0.25;
+
+        let Some((center, _)) = projected else {
+            return;
+        };
+        if self.visibility < 0.01 {
+            return;
+        }
+
+        let screen_center = Vec2::new(framebuffer.width as f32 * 0.5, framebuffer.height as f32 * 0.5);
+        let light = center.xy();
+        let axis = screen_center - light;
+
+        // Fainter as the light drifts towards the edge of the frame
+        let off_center = axis.magnitude() / screen_center.magnitude();
+        let strength = self.visibility * self.intensity * (1.0 - off_center * 0.5).max(0.2);
+
+        for (t, radius, hex) in GHOSTS.iter() {
+            let ghost = light + axis * *t;
+            let color = Color::from_hex(*hex) * tint;
+            draw_disk(framebuffer, &ghost, *radius, &(color * strength), 1.5);
+        }
+
+        draw_disk(framebuffer, &light, 90.0, &(tint * (strength * 0.8)), 2.5);
+    }
+}
+
+// Soft additive disk drawn over everything; falloff controls how sharp the edge is
+fn draw_disk(framebuffer: &mut Framebuffer, center: &Vec2, radius: f32, color: &Color, falloff: f32) {
+    let min_x = (center.x - radius).max(0.0) as usize;
+    let min_y = (center.y - radius).max(0.0) as usize;
+    let max_x = (center.x + radius).min(framebuffer.width as f32 - 1.0);
+    let max_y = (center.y + radius).min(framebuffer.height as f32 - 1.0);
+    if max_x < 0.0 || max_y < 0.0 {
+        return;
+    }
+
+    for y in min_y..=max_y as usize {
+        for x in min_x..=max_x as usize {
+            let dx = x as f32 + 0.5 - center.x;
+            let dy = y as f32 + 0.5 - center.y;
+            let r = (dx * dx + dy * dy).sqrt() / radius;
+            if r >= 1.0 {
+                continue;
+            }
+
+            let amount = (1.0 - r).powf(falloff);
+            framebuffer.blend_add(x, y, f32::NEG_INFINITY, &(*color * amount));
+        }
+    }
+}
@@ -0,0 +1,276 @@
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::shaders::planet_shaders::smoothstep;
+use crate::uniforms::Uniforms;
+use nalgebra_glm::{Vec2, Vec3};
+
+// Radius of a black hole's shadow in Schwarzschild radii (the photon capture
+// radius, sqrt(27) / 2)
+const SHADOW_RADIUS: f32 = 2.6;
+// How far out the lens bends the frame, in Einstein radii; it fades to
+// nothing over the last stretch so there is no visible seam
+const REACH: f32 = 6.0;
+// Longest side of the patch one pixel is stretched into, in pixels
+const MAX_STRETCH: f32 = 24.0;
+
+// Screen-space gravitational lens, run after the skybox and the scene have
+// been drawn. Pixels nearer than the hole are left alone; everything behind it
+// is moved to where the thin-lens equation says its light appears, so stars
+// and planets behind it turn into arcs and an Einstein ring, with a mirrored
+// second image inside. How much light bends depends on how far behind the hole
+// its source is, so the sky is pulled back from the hole's surroundings, while
+// the far side of its own disk, close behind it, wraps tightly over the shadow.
+pub struct GravitationalLens {
+    // The lens's region of the frame as it was before bending
+    source: Vec<u32>,
+    // Per pixel of the region, how far behind the hole the surface now shown
+    // there lies, as a fraction of its distance; nearer ones win. Negative
+    // where nothing is bent.
+    shown: Vec<f32>,
+    // The same for the frame before bending, only for solid surfaces behind the hole
+    behind: Vec<f32>,
+}
+
+// The rectangle of the screen within the lens's reach. Nothing outside it
+// changes, so only it is copied and tracked.
+struct Region {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+}
+
+impl Region {
+    // Index into the region's buffers of a screen pixel, if it falls inside
+    fn index(&self, x: usize, y: usize) -> Option<usize> {
+        (x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height)
+            .then(|| (y - self.y) * self.width + x - self.x)
+    }
+}
+
+// Where the hole sits on screen and how strongly it bends light, in pixels
+struct Lens {
+    center: Vec3,
+    einstein: f32,
+    shadow: f32,
+    reach: f32,
+}
+
+impl Lens {
+    // Squared Einstein radius at `distance` px from the centre for a source
+    // infinitely far behind; nearer sources are bent proportionally less
+    fn strength(&self, distance: f32) -> f32 {
+        self.einstein * self.einstein * smoothstep(self.reach, self.reach * 0.6, distance)
+    }
+}
+
+impl GravitationalLens {
+    pub fn new() -> Self {
+        GravitationalLens {
+            source: Vec::new(),
+            shown: Vec::new(),
+            behind: Vec::new(),
+        }
+    }
+
+    pub fn render(&mut self, position: &Vec3, schwarzschild_radius: f32, framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
+        let Some(center) = uniforms.world_to_screen(position) else {
+            return;
+        };
+        if !(-1.0..=1.0).contains(&center.z) {
+            return;
+        }
+        let Some(edge) = uniforms.world_to_screen(&(position + uniforms.camera_right() * schwarzschild_radius)) else {
+            return;
+        };
+        let horizon_px = (edge.xy() - center.xy()).magnitude();
+
+        // For a background far behind the lens the Einstein angle is
+        // sqrt(2 * rs / distance); the focal length turns both into pixels
+        let focal = uniforms.projection_matrix[(1, 1)] * uniforms.viewport_matrix[(1, 1)].abs();
+        let einstein = (2.0 * horizon_px * focal).sqrt();
+        if einstein < 1.0 {
+            return;
+        }
+        let lens = Lens {
+            center,
+            einstein,
+            shadow: horizon_px * SHADOW_RADIUS,
+            reach: einstein * REACH,
+        };
+        let lens_distance = view_distance(center.z, uniforms);
+
+        let min_x = (center.x - lens.reach).max(0.0) as usize;
+        let min_y = (center.y - lens.reach).max(0.0) as usize;
+        let max_x = (center.x + lens.reach).min(framebuffer.width as f32 - 1.0);
+        let max_y = (center.y + lens.reach).min(framebuffer.height as f32 - 1.0);
+        if max_x < 0.0 || max_y < 0.0 {
+            return;
+        }
+        let (max_x, max_y) = (max_x as usize, max_y as usize);
+        if min_x > max_x || min_y > max_y {
+            return;
+        }
+
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        let region = Region {
+            x: min_x,
+            y: min_y,
+            width: max_x - min_x + 1,
+            height: max_y - min_y + 1,
+        };
+        self.source.clear();
+        for y in min_y..=max_y {
+            self.source.extend_from_slice(&framebuffer.buffer[y * width + min_x..=y * width + max_x]);
+        }
+        self.shown.clear();
+        self.shown.resize(region.width * region.height, -1.0);
+        self.behind.clear();
+        self.behind.resize(region.width * region.height, -1.0);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let depth = framebuffer.zbuffer[y * width + x];
+                if depth >= 1.0 || depth < center.z {
+                    continue;
+                }
+                let behind = view_distance(depth, uniforms) - lens_distance;
+                if behind > 0.0 {
+                    self.behind[(y - min_y) * region.width + x - min_x] = behind / (behind + lens_distance);
+                }
+            }
+        }
+
+        // The sky: look up where each pixel's light comes from. A thin-lens
+        // image at angle theta shows the source at theta - e^2 / theta, which
+        // lands on the far side of the hole inside the Einstein ring.
+        let empty_sky = Color::DARK_BG.to_hex();
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let index = y * width + x;
+                if framebuffer.zbuffer[index] < center.z {
+                    continue;
+                }
+                let offset = Vec2::new(x as f32 + 0.5 - center.x, y as f32 + 0.5 - center.y);
+                let distance = offset.magnitude();
+                if distance >= lens.reach {
+                    continue;
+                }
+                self.shown[(y - min_y) * region.width + x - min_x] = 1.0;
+                if distance < lens.shadow {
+                    framebuffer.buffer[index] = Color::BLACK.to_hex();
+                    continue;
+                }
+
+                let source = center.xy() + offset * (1.0 - lens.strength(distance) / (distance * distance));
+                let inside = source.x >= 0.0 && source.y >= 0.0 && (source.x as usize) < width && (source.y as usize) < height;
+                // Anything solid found there is placed by the pass below instead.
+                // Outside the region the frame is still as it was.
+                framebuffer.buffer[index] = match inside.then(|| (source.x as usize, source.y as usize)) {
+                    Some((sx, sy)) if framebuffer.zbuffer[sy * width + sx] >= 1.0 => match region.index(sx, sy) {
+                        Some(sample) => self.source[sample],
+                        None => framebuffer.buffer[sy * width + sx],
+                    },
+                    _ => empty_sky,
+                };
+            }
+        }
+
+        // Solid surfaces behind the hole: every pixel is carried to both of its
+        // images and stretched as much as the lens magnifies it there
+        let source_behind = &self.behind;
+        let share_at = |x: usize, y: usize| region.index(x, y).map(|index| source_behind[index]).filter(|&share| share >= 0.0);
+        // Signed distance from the centre of the image of a point `distance` px
+        // out; negative means the mirrored image on the opposite side
+        let image_of = |distance: f32, share: f32, side: f32| {
+            let spread = (distance * distance + 4.0 * lens.strength(distance) * share).sqrt();
+            (distance + side * spread) * 0.5
+        };
+
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let Some(share) = share_at(x, y) else {
+                    continue;
+                };
+                let offset = Vec2::new(x as f32 + 0.5 - center.x, y as f32 + 0.5 - center.y);
+                let distance = offset.magnitude().max(0.5);
+                let outward = offset / distance;
+
+                // Neighbours at other depths are bent by other amounts, so the
+                // patch reaches out to where theirs land to leave no gaps
+                let neighbours = [-1.0, 1.0].map(|step| {
+                    let next = offset + center.xy() + outward * step;
+                    let inside = next.x >= 0.0 && next.y >= 0.0 && (next.x as usize) < width && (next.y as usize) < height;
+                    inside.then(|| share_at(next.x as usize, next.y as usize)).flatten().map(|share| ((distance + step).max(0.5), share))
+                });
+
+                for side in [1.0, -1.0] {
+                    let image = image_of(distance, share, side);
+                    let reach = neighbours
+                        .iter()
+                        .flatten()
+                        .map(|&(distance, share)| (image_of(distance, share, side) - image).abs())
+                        .fold(1.0, f32::max);
+                    let patch = Patch {
+                        at: center.xy() + outward * image,
+                        outward,
+                        radial: reach.min(MAX_STRETCH),
+                        tangential: (image.abs() / distance).clamp(1.0, MAX_STRETCH),
+                    };
+                    let color = self.source[(y - min_y) * region.width + x - min_x];
+                    splat(&mut self.shown, &region, framebuffer, &lens, &patch, share, color);
+                }
+            }
+        }
+    }
+}
+
+// Paints the patch wherever nothing nearer has been placed yet
+fn splat(shown: &mut [f32], region: &Region, framebuffer: &mut Framebuffer, lens: &Lens, patch: &Patch, share: f32, color: u32) {
+    let half = patch.radial.max(patch.tangential) * 0.5 + 0.5;
+    let min_x = (patch.at.x - half).max(0.0) as usize;
+    let min_y = (patch.at.y - half).max(0.0) as usize;
+    let max_x = (patch.at.x + half).min(framebuffer.width as f32 - 1.0);
+    let max_y = (patch.at.y + half).min(framebuffer.height as f32 - 1.0);
+    if max_x < 0.0 || max_y < 0.0 {
+        return;
+    }
+    let across = Vec2::new(-patch.outward.y, patch.outward.x);
+
+    for y in min_y..=max_y as usize {
+        for x in min_x..=max_x as usize {
+            let Some(local) = region.index(x, y) else {
+                continue;
+            };
+            if shown[local] < 0.0 || share >= shown[local] {
+                continue;
+            }
+            let pixel = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let offset = pixel - patch.at;
+            if offset.dot(&patch.outward).abs() > patch.radial * 0.5 + 0.5
+                || offset.dot(&across).abs() > patch.tangential * 0.5 + 0.5
+            {
+                continue;
+            }
+            if (pixel - lens.center.xy()).magnitude() < lens.shadow {
+                continue;
+            }
+            shown[local] = share;
+            framebuffer.buffer[y * framebuffer.width + x] = color;
+        }
+    }
+}
+
+// A source pixel's image: a rectangle stretched along and across the direction
+// away from the hole, in pixels
+struct Patch {
+    at: Vec2,
+    outward: Vec2,
+    radial: f32,
+    tangential: f32,
+}
+
+// Distance along the view axis for an NDC depth, undoing the perspective projection
+fn view_distance(depth: f32, uniforms: &Uniforms) -> f32 {
+    let projection = &uniforms.projection_matrix;
+    projection[(2, 3)] / (depth + projection[(2, 2)])
+}
@@ -9,25 +9,96 @@ mod shaders;
 mod geometry;
 mod celestial;
 mod warp;
+mod particles;
 mod skybox;
-
-use minifb::{Key, Window, WindowOptions};
+mod scene;
+mod physics;
+mod clock;
+mod ephemeris;
+mod corona;
+mod lens_flare;
+mod lensing;
+mod scene_file;
+mod generator;
+mod eclipses;
+mod orbit_camera;
+mod camera_path;
+mod autopilot;
+mod follow_camera;
+
+use minifb::{Key, MouseButton, MouseMode, Window, WindowOptions};
 use nalgebra_glm::Vec3;
-use std::time::Instant;
 use std::f32::consts::PI;
 
 use framebuffer::Framebuffer;
 use camera::Camera;
-use uniforms::{Uniforms, create_viewport_matrix, create_projection_matrix, create_model_matrix};
-use pipeline::triangle_3d;
-use celestial::{Planet, PlanetShader, Star, Ship};
+use uniforms::{Uniforms, create_viewport_matrix, create_projection_matrix};
+use pipeline::render_mesh;
+use celestial::{Ship, ShipControls};
 use warp::WarpEffect;
 use skybox::Skybox;
+use physics::Integrator;
+use clock::SimulationClock;
+use ephemeris::ScaleCompression;
+use corona::Corona;
+use lens_flare::LensFlare;
+use lensing::GravitationalLens;
+use scene_file::StarSystem;
+use eclipses::EclipseMonitor;
+use orbit_camera::OrbitCamera;
+use camera_path::CameraPath;
+use autopilot::Autopilot;
+use follow_camera::CameraMode;
 
 const WIDTH: usize = 800;
 const HEIGHT: usize = 600;
 
+const DEFAULT_SCENE: &str = "scenes/default.toml";
+const SAVE_PATH: &str = "scenes/saved.toml";
+const DEFAULT_OUTPUT: &str = "frames";
+// Seeds the sky when rendering a camera path, so runs match frame for frame
+const RENDER_SEED: u64 = 1;
+// Radians the camera turns per pixel of mouse movement
+const MOUSE_SENSITIVITY: f32 = 0.004;
+// Radians per frame while Q or E is held
+const ROLL_SPEED: f32 = 0.03;
+// Pixels of mouse movement per frame that fire the ship's RCS at full
+const SHIP_MOUSE_RANGE: f32 = 20.0;
+
+// 0 returns to free flight, 1 to 9 pick a body to orbit
+const DIGIT_KEYS: [Key; 10] = [
+    Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4,
+    Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+];
+const MOVEMENT_KEYS: [Key; 6] = [Key::W, Key::S, Key::A, Key::D, Key::Space, Key::LeftShift];
+
+// Vertical field of view, unless a camera path sets its own
+const FOV: f32 = 45.0 * PI / 180.0;
+
+// How many upcoming eclipses the L key lists
+const MAX_LISTED_ECLIPSES: usize = 12;
+
 fn main() {
+    let options = Options::parse(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+    let camera_path = options.camera_path.as_ref().map(|path| {
+        CameraPath::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        })
+    });
+
+    if options.render_path {
+        let path = camera_path.expect("--render-path always comes with a path");
+        if let Err(e) = render_path(&options, &path) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
     
     let mut window = Window::new(
@@ -42,51 +113,65 @@ fn main() {
 
     window.limit_update_rate(Some(std::time::Duration::from_micros(16600)));
 
-    let mut camera = Camera::new(
-        Vec3::new(0.0, 0.0, -10.0),
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-    );
+    let mut seed = options.seed.unwrap_or_else(generator::random_seed);
+    let StarSystem { mut scene, mut camera, skybox_stars } = load_system(&options, seed);
 
     let mut ship = Ship::new("assets/models/ship.obj")
         .unwrap_or_else(|e| {
             panic!("Failed to load ship model: {}", e);
         });
     ship.scale = 0.01;
-
-    let mut sun = Star::new(1.5, Vec3::new(0.0, 0.0, 0.0));
-
-    let mut planets = vec![
-        Planet::new(PlanetShader::Rocky, 0.4, 4.0, 0.8),
-        Planet::new(PlanetShader::Lava, 0.6, 6.0, 0.6),
-        Planet::new(PlanetShader::Rocky, 0.7, 8.0, 0.5),
-        Planet::new(PlanetShader::Rocky, 0.5, 10.0, 0.4),
-        Planet::new(PlanetShader::Gaseous, 1.5, 14.0, 0.3),
-        Planet::new(PlanetShader::Gaseous, 1.3, 18.0, 0.25),
-        Planet::new(PlanetShader::Gaseous, 0.9, 22.0, 0.2),
-        Planet::new(PlanetShader::Gaseous, 0.85, 26.0, 0.15),
-    ];
+    launch_ship(&mut ship, &camera);
+    let mut sun_id = scene.primary_star().expect("every scene has a star");
 
     let mut warp_effect = WarpEffect::new();
-
-    let skybox = Skybox::new(1000);
+    let mut effects = Effects::new(Skybox::new(skybox_stars));
 
     let mut uniforms = Uniforms::new();
-    uniforms.projection_matrix = create_projection_matrix(
-        45.0 * PI / 180.0,
-        WIDTH as f32 / HEIGHT as f32,
-        0.1,
-        100.0,
-    );
+    uniforms.projection_matrix = projection(FOV);
     uniforms.view_matrix = camera.get_view_matrix();
     uniforms.viewport_matrix = create_viewport_matrix(WIDTH as f32, HEIGHT as f32);
 
-    let start_time = Instant::now();
+    let mut clock = SimulationClock::new(1.0 / 60.0);
+    if let Some(seconds) = options.date {
+        clock.time = seconds;
+        scene.time = seconds;
+        scene.update(0.0);
+    }
+    // The real solar system counts from J2000, so its times read as dates
+    let mut dated = options.scale.is_some();
+    print_eclipses(&scene, dated);
+    let mut eclipse_monitor = EclipseMonitor::new(&scene);
     let mut f_key_was_pressed = false;
+    let mut c_key_was_pressed = false;
+    let mut n_key_was_pressed = false;
+    let mut i_key_was_pressed = false;
+    let mut p_key_was_pressed = false;
+    let mut r_key_was_pressed = false;
+    let mut plus_key_was_pressed = false;
+    let mut minus_key_was_pressed = false;
+    let mut save_key_was_pressed = false;
+    let mut g_key_was_pressed = false;
+    let mut l_key_was_pressed = false;
+    let mut tab_key_was_pressed = false;
+    let mut digit_key_was_pressed: Option<usize> = None;
+    let mut last_mouse: Option<(f32, f32)> = None;
+    // Set while the camera is locked onto a body
+    let mut orbit_camera: Option<OrbitCamera> = None;
+    let mut v_key_was_pressed = false;
+    // Seconds into the camera path while it plays; it starts right away
+    let mut path_playback: Option<f32> = camera_path.as_ref().map(|_| 0.0);
+    let mut t_key_was_pressed = false;
+    let mut autopilot: Option<Autopilot> = None;
+    let mut m_key_was_pressed = false;
+    let mut x_key_was_pressed = false;
+    // A path given on the command line plays from the camera, not the ship
+    let mut camera_mode = if path_playback.is_some() { CameraMode::Free } else { CameraMode::Chase };
+    let mut integrator = Integrator::Leapfrog;
+    let mut frame_count: u64 = 0;
 
     while window.is_open() && !window.is_key_down(Key::Escape) {
-        let elapsed = start_time.elapsed().as_secs_f32();
-        uniforms.time = elapsed;
+        clock.tick();
 
         let camera_speed = if warp_effect.active { 0.5 } else { 0.1 };
 
@@ -99,120 +184,631 @@ fn main() {
             f_key_was_pressed = false;
         }
 
-        if window.is_key_down(Key::W) {
-            camera.move_forward(camera_speed);
+        if window.is_key_down(Key::C) {
+            if !c_key_was_pressed {
+                if let Some(sun) = scene.star_mut(sun_id) {
+                    sun.set_class(sun.class().next());
+                }
+                c_key_was_pressed = true;
+            }
+        } else {
+            c_key_was_pressed = false;
         }
-        if window.is_key_down(Key::S) {
-            camera.move_forward(-camera_speed);
+
+        if window.is_key_down(Key::N) {
+            if !n_key_was_pressed {
+                if scene.physics.is_some() {
+                    scene.disable_physics();
+                } else if dated {
+                    // Its distances are compressed but its periods aren't, so
+                    // the masses can't keep the planets on their orbits
+                    println!("N-body gravity can't run on the compressed solar system");
+                } else {
+                    scene.enable_physics(integrator);
+                    if let Some(physics) = &scene.physics {
+                        if clock.limit_scale(clock.scale_limit(physics.max_time_step())) {
+                            println!("Time {} (gravity can't be integrated accurately any faster)", clock.describe());
+                        }
+                    }
+                    for id in scene.loose_moons() {
+                        let node = &scene.nodes[id];
+                        let parent = node.parent.map(|p| scene.nodes[p].name.as_str()).unwrap_or("");
+                        println!("{} orbits too far out to stay bound to {}", node.name, parent);
+                    }
+                }
+                n_key_was_pressed = true;
+            }
+        } else {
+            n_key_was_pressed = false;
         }
-        if window.is_key_down(Key::A) {
-            camera.move_right(-camera_speed);
+
+        if window.is_key_down(Key::I) {
+            if !i_key_was_pressed {
+                integrator = integrator.next();
+                if let Some(physics) = &mut scene.physics {
+                    physics.integrator = integrator;
+                }
+                println!("Integrator: {}", integrator.name());
+                i_key_was_pressed = true;
+            }
+        } else {
+            i_key_was_pressed = false;
         }
-        if window.is_key_down(Key::D) {
-            camera.move_right(camera_speed);
+
+        if window.is_key_down(Key::P) {
+            if !p_key_was_pressed {
+                clock.toggle_pause();
+                println!("Time {}", clock.describe());
+                p_key_was_pressed = true;
+            }
+        } else {
+            p_key_was_pressed = false;
         }
-        if window.is_key_down(Key::Q) {
-            camera.move_up(camera_speed);
+
+        if window.is_key_down(Key::R) {
+            if !r_key_was_pressed {
+                clock.toggle_reverse();
+                println!("Time {}", clock.describe());
+                r_key_was_pressed = true;
+            }
+        } else {
+            r_key_was_pressed = false;
+        }
+
+        if window.is_key_down(Key::Equal) {
+            if !plus_key_was_pressed {
+                let limit = scene
+                    .physics
+                    .as_ref()
+                    .map_or(f64::INFINITY, |physics| clock.scale_limit(physics.max_time_step()));
+                if clock.faster(limit) {
+                    println!("Time {}", clock.describe());
+                } else {
+                    println!("Time {} (gravity can't be integrated accurately any faster)", clock.describe());
+                }
+                plus_key_was_pressed = true;
+            }
+        } else {
+            plus_key_was_pressed = false;
         }
-        if window.is_key_down(Key::E) {
-            camera.move_up(-camera_speed);
+
+        if window.is_key_down(Key::Minus) {
+            if !minus_key_was_pressed {
+                clock.slower();
+                println!("Time {}", clock.describe());
+                minus_key_was_pressed = true;
+            }
+        } else {
+            minus_key_was_pressed = false;
         }
 
-        uniforms.view_matrix = camera.get_view_matrix();
+        if window.is_key_down(Key::F5) {
+            if !save_key_was_pressed {
+                match scene_file::save(SAVE_PATH, &scene, &camera, skybox_stars) {
+                    Ok(()) => println!("Scene saved to {}", SAVE_PATH),
+                    Err(e) => eprintln!("{}", e),
+                }
+                save_key_was_pressed = true;
+            }
+        } else {
+            save_key_was_pressed = false;
+        }
 
-        sun.update(0.016);
+        if window.is_key_down(Key::G) {
+            if !g_key_was_pressed {
+                seed = seed.wrapping_add(1);
+                let system = generator::generate(seed);
+                scene = system.scene;
+                dated = false;
+                camera = system.camera;
+                sun_id = scene.primary_star().expect("generated systems have a star");
+                eclipse_monitor = EclipseMonitor::new(&scene);
+                orbit_camera = None;
+                autopilot = None;
+                launch_ship(&mut ship, &camera);
+                println!("System seed {}", seed);
+                g_key_was_pressed = true;
+            }
+        } else {
+            g_key_was_pressed = false;
+        }
 
-        for planet in &mut planets {
-            planet.update(0.016);
+        if window.is_key_down(Key::L) {
+            if !l_key_was_pressed {
+                print_eclipses(&scene, dated);
+                l_key_was_pressed = true;
+            }
+        } else {
+            l_key_was_pressed = false;
         }
 
-        ship.update(&camera);
+        if window.is_key_down(Key::Tab) {
+            if !tab_key_was_pressed {
+                let targets = orbit_camera::targets(&scene);
+                let next = match &orbit_camera {
+                    Some(orbit) => targets.iter().position(|&id| id == orbit.target).map_or(0, |i| (i + 1) % targets.len()),
+                    None => 0,
+                };
+                if let Some(&target) = targets.get(next) {
+                    camera_mode = CameraMode::Free;
+                    orbit_camera = Some(OrbitCamera::new(&scene, target, &camera));
+                    println!("Orbiting {}", scene.nodes[target].name);
+                }
+                tab_key_was_pressed = true;
+            }
+        } else {
+            tab_key_was_pressed = false;
+        }
 
-        warp_effect.update(0.016, &camera);
+        let digit = DIGIT_KEYS.iter().position(|&key| window.is_key_down(key));
+        if digit.is_some() && digit != digit_key_was_pressed {
+            match digit {
+                Some(0) => {
+                    orbit_camera = None;
+                    println!("Free flight");
+                }
+                Some(n) => {
+                    if let Some(&target) = orbit_camera::targets(&scene).get(n - 1) {
+                        camera_mode = CameraMode::Free;
+                        orbit_camera = Some(OrbitCamera::new(&scene, target, &camera));
+                        println!("Orbiting {}", scene.nodes[target].name);
+                    }
+                }
+                None => {}
+            }
+        }
+        digit_key_was_pressed = digit;
+
+        if window.is_key_down(Key::T) {
+            if !t_key_was_pressed {
+                if autopilot.take().is_some() {
+                    warp_effect.set_active(false);
+                    println!("Autopilot off");
+                } else if let Some(target) = autopilot::target_in_view(&scene, &camera) {
+                    orbit_camera = None;
+                    path_playback = None;
+                    // Watch the ship fly, from wherever it is flown from
+                    if camera_mode == CameraMode::Free {
+                        camera_mode = CameraMode::Chase;
+                    }
+                    uniforms.projection_matrix = projection(FOV);
+                    autopilot = Some(Autopilot::new(&scene, target, &ship));
+                    println!("Autopilot: heading for {}", scene.nodes[target].name);
+                }
+                t_key_was_pressed = true;
+            }
+        } else {
+            t_key_was_pressed = false;
+        }
 
-        framebuffer.clear();
+        if window.is_key_down(Key::M) {
+            if !m_key_was_pressed {
+                camera_mode = camera_mode.next();
+                if camera_mode != CameraMode::Free {
+                    orbit_camera = None;
+                    if path_playback.take().is_some() {
+                        uniforms.projection_matrix = projection(FOV);
+                    }
+                }
+                println!("Camera: {}", camera_mode.name());
+                m_key_was_pressed = true;
+            }
+        } else {
+            m_key_was_pressed = false;
+        }
 
-        skybox.render(&mut framebuffer, &uniforms);
+        if window.is_key_down(Key::X) {
+            if !x_key_was_pressed {
+                ship.flight_assist = !ship.flight_assist;
+                println!("Flight assist {}", if ship.flight_assist { "on" } else { "off" });
+                x_key_was_pressed = true;
+            }
+        } else {
+            x_key_was_pressed = false;
+        }
 
-        uniforms.model_matrix = create_model_matrix(
-            sun.position,
-            1.0,
-            Vec3::new(sun.rotation, sun.rotation * 0.5, 0.0),
-        );
-        uniforms.is_star = true;
-        uniforms.planet_shader = None;
+        let flying_ship = camera_mode != CameraMode::Free;
 
-        for i in (0..sun.mesh.vertices.len()).step_by(3) {
-            if i + 2 < sun.mesh.vertices.len() {
-                let v1 = &sun.mesh.vertices[i];
-                let v2 = &sun.mesh.vertices[i + 1];
-                let v3 = &sun.mesh.vertices[i + 2];
+        // Flying off by hand lets go of the body being orbited
+        if !flying_ship && MOVEMENT_KEYS.iter().any(|&key| window.is_key_down(key)) && orbit_camera.take().is_some() {
+            println!("Free flight");
+        }
 
-                triangle_3d(v1, v2, v3, &uniforms, &mut framebuffer);
+        if window.is_key_down(Key::V) {
+            if !v_key_was_pressed {
+                if camera_path.is_none() {
+                    println!("No camera path loaded, start with --camera-path <file>");
+                } else if path_playback.take().is_some() {
+                    uniforms.projection_matrix = projection(FOV);
+                } else {
+                    path_playback = Some(0.0);
+                    orbit_camera = None;
+                    camera_mode = CameraMode::Free;
+                }
+                v_key_was_pressed = true;
             }
+        } else {
+            v_key_was_pressed = false;
         }
 
-        for planet in &planets {
-            uniforms.model_matrix = create_model_matrix(
-                planet.position,
-                1.0,
-                Vec3::new(planet.rotation, planet.rotation * 0.7, 0.0),
-            );
-            uniforms.is_star = false;
-            uniforms.planet_shader = Some(planet.shader_type);
-
-            for i in (0..planet.mesh.vertices.len()).step_by(3) {
-                if i + 2 < planet.mesh.vertices.len() {
-                    let v1 = &planet.mesh.vertices[i];
-                    let v2 = &planet.mesh.vertices[i + 1];
-                    let v3 = &planet.mesh.vertices[i + 2];
+        // In the ship's cameras the same keys fire its engines and thrusters
+        let mut controls = ShipControls::default();
+        let axis = |positive: Key, negative: Key| {
+            window.is_key_down(positive) as i32 as f32 - window.is_key_down(negative) as i32 as f32
+        };
+        if flying_ship {
+            controls.thrust = Vec3::new(axis(Key::D, Key::A), axis(Key::Space, Key::LeftShift), axis(Key::W, Key::S));
+            controls.rotation.z = axis(Key::E, Key::Q);
+        } else {
+            if window.is_key_down(Key::W) {
+                camera.move_forward(camera_speed);
+            }
+            if window.is_key_down(Key::S) {
+                camera.move_forward(-camera_speed);
+            }
+            if window.is_key_down(Key::A) {
+                camera.move_right(-camera_speed);
+            }
+            if window.is_key_down(Key::D) {
+                camera.move_right(camera_speed);
+            }
+            if window.is_key_down(Key::Space) {
+                camera.move_up(camera_speed);
+            }
+            if window.is_key_down(Key::LeftShift) {
+                camera.move_up(-camera_speed);
+            }
+            if window.is_key_down(Key::Q) {
+                camera.rotate(0.0, 0.0, -ROLL_SPEED);
+            }
+            if window.is_key_down(Key::E) {
+                camera.rotate(0.0, 0.0, ROLL_SPEED);
+            }
+        }
 
-                    triangle_3d(v1, v2, v3, &uniforms, &mut framebuffer);
+        // Look around, steer the ship, or swing around the orbited body, while the
+        // left button is held; minifb can't capture the cursor, so this works on
+        // deltas between frames
+        let mouse = window.get_mouse_pos(MouseMode::Pass);
+        if window.get_mouse_down(MouseButton::Left) {
+            if let (Some((x, y)), Some((last_x, last_y))) = (mouse, last_mouse) {
+                let (yaw, pitch) = (-(x - last_x) * MOUSE_SENSITIVITY, -(y - last_y) * MOUSE_SENSITIVITY);
+                if flying_ship {
+                    controls.rotation.x = -(y - last_y) / SHIP_MOUSE_RANGE;
+                    controls.rotation.y = -(x - last_x) / SHIP_MOUSE_RANGE;
+                } else {
+                    match &mut orbit_camera {
+                        Some(orbit) => orbit.rotate(&mut camera, yaw, pitch),
+                        None => camera.rotate(yaw, pitch, 0.0),
+                    }
                 }
             }
         }
+        last_mouse = mouse;
 
-        uniforms.model_matrix = ship.get_model_matrix();
-        uniforms.is_star = false;
-        uniforms.planet_shader = None;
+        // Taking the controls lets go of the autopilot
+        if autopilot.is_some() && (controls.thrust != Vec3::zeros() || controls.rotation != Vec3::zeros()) {
+            autopilot = None;
+            warp_effect.set_active(false);
+            println!("Autopilot off");
+        }
+        controls.boost = warp_effect.active;
 
-        for i in (0..ship.mesh.vertices.len()).step_by(3) {
-            if i + 2 < ship.mesh.vertices.len() {
-                let v1 = &ship.mesh.vertices[i];
-                let v2 = &ship.mesh.vertices[i + 1];
-                let v3 = &ship.mesh.vertices[i + 2];
+        if let (Some(orbit), Some((_, scroll))) = (&mut orbit_camera, window.get_scroll_wheel()) {
+            orbit.zoom(&scene, scroll.clamp(-3.0, 3.0));
+        }
 
-                triangle_3d(v1, v2, v3, &uniforms, &mut framebuffer);
+        // A playing path runs the simulation at its own pace, as it will when rendered
+        let time_scale = match (&camera_path, path_playback) {
+            (Some(path), Some(_)) => {
+                let limit = scene
+                    .physics
+                    .as_ref()
+                    .map_or(f64::INFINITY, |physics| clock.scale_limit(physics.max_time_step()));
+                path.time_scale.min(limit)
+            }
+            _ => clock.time_scale(),
+        };
+        // The ship flies on the bodies' clock, so it falls at the pace they
+        // move; its time runs forward even while theirs runs backwards
+        let landmarks = orbit_camera::targets(&scene);
+        if autopilot.is_none() {
+            ship.steer(&controls, clock.real_delta());
+        }
+        let mut elapsed = 0.0;
+        while let Some(delta_time) = clock.step_at(time_scale) {
+            scene.update(delta_time);
+            elapsed += delta_time.abs() as f32;
+            if autopilot.is_none() {
+                ship.update(&controls, |point| scene.gravity_at(point), delta_time.abs() as f32);
+                for &id in &landmarks {
+                    let node = &scene.nodes[id];
+                    ship.collide(&node.world_position, node.body.radius());
+                }
             }
         }
 
-        for particle in &warp_effect.particles {
-            let pos_4d = nalgebra_glm::vec3_to_vec4(&particle.position);
-            let clip_pos = uniforms.projection_matrix * uniforms.view_matrix * pos_4d;
+        // The autopilot moves the ship itself, in place of its physics
+        if let Some(pilot) = &mut autopilot {
+            let phase = pilot.phase;
+            let parked = pilot.update(&scene, &mut ship, &mut warp_effect, clock.real_delta(), elapsed);
+            if parked {
+                println!("Autopilot: holding station at {}", scene.nodes[pilot.target].name);
+                warp_effect.set_active(false);
+            } else if pilot.phase != phase {
+                println!("Autopilot: {}", pilot.phase.name());
+            }
+        }
+        if let Some(orbit) = &mut orbit_camera {
+            orbit.update(&scene, &mut camera, clock.real_delta());
+        }
+        if let (Some(path), Some(elapsed)) = (&camera_path, &mut path_playback) {
+            *elapsed += clock.real_delta();
+            let pose = path.sample(*elapsed);
+            camera.look_at(pose.position, pose.target);
+            uniforms.projection_matrix = projection(pose.fov);
+            if *elapsed > path.duration() {
+                path_playback = None;
+                uniforms.projection_matrix = projection(FOV);
+                println!("Camera path finished");
+            }
+        }
 
-            if clip_pos.w > 0.0 {
-                let ndc = clip_pos / clip_pos.w;
+        camera_mode.follow(&ship, &mut camera, clock.real_delta());
 
-                if ndc.x >= -1.0 && ndc.x <= 1.0 && ndc.y >= -1.0 && ndc.y <= 1.0 && ndc.z >= 0.0 && ndc.z <= 1.0 {
-                    let screen = uniforms.viewport_matrix * ndc;
-                    let x = screen.x as usize;
-                    let y = screen.y as usize;
+        uniforms.view_matrix = camera.get_view_matrix();
+        uniforms.camera_position = camera.eye;
+        uniforms.time = clock.shader_time();
 
-                    if x < WIDTH && y < HEIGHT {
-                        for dy in 0..8 {
-                            for dx in 0..8 {
-                                if x + dx < WIDTH && y + dy < HEIGHT {
-                                    framebuffer.point_with_depth(x + dx, y + dy, ndc.z, &particle.color);
-                                }
-                            }
-                        }
-                    }
-                }
+        for message in eclipse_monitor.update(&scene) {
+            println!("[{}] {}", timestamp(scene.time, dated), message);
+        }
+
+        frame_count += 1;
+        if let Some(physics) = &scene.physics {
+            if frame_count.is_multiple_of(120) {
+                println!(
+                    "n-body ({}) t = {:.1}s, energy drift {:+.3e}",
+                    physics.integrator.name(),
+                    physics.time,
+                    physics.energy_drift()
+                );
             }
         }
 
+        uniforms.lights = scene.lights();
+
+        warp_effect.update(clock.real_delta(), &camera);
+
+        effects.render_world(&scene, &mut framebuffer, &mut uniforms);
+
+        // From the cockpit the hull would fill the view
+        if camera_mode != CameraMode::Cockpit {
+            uniforms.model_matrix = ship.get_model_matrix();
+            uniforms.is_star = false;
+            uniforms.planet_shader = None;
+            uniforms.terrain = None;
+
+            render_mesh(&ship.mesh, &uniforms, &mut framebuffer);
+        }
+
+        warp_effect.render(&mut framebuffer, &uniforms);
+
+        effects.render_glare(&scene, sun_id, &mut framebuffer, &uniforms);
+
         window
             .update_with_buffer(&framebuffer.buffer, WIDTH, HEIGHT)
             .unwrap();
     }
 }
+
+// Parks the ship just ahead of the camera, at rest and facing the same way
+fn launch_ship(ship: &mut Ship, camera: &Camera) {
+    ship.position = follow_camera::launch_point(camera);
+    ship.orientation = camera.orientation;
+    ship.velocity = Vec3::zeros();
+    ship.angular_velocity = Vec3::zeros();
+}
+
+fn load_system(options: &Options, seed: u64) -> StarSystem {
+    if let Some(scale) = &options.scale {
+        StarSystem {
+            scene: ephemeris::solar_system(scale),
+            camera: Camera::new(Vec3::new(0.0, 0.0, -10.0), Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)),
+            skybox_stars: 1000,
+        }
+    } else if options.seed.is_some() {
+        println!("System seed {}", seed);
+        generator::generate(seed)
+    } else {
+        scene_file::load(&options.scene).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        })
+    }
+}
+
+fn projection(fov: f32) -> nalgebra_glm::Mat4 {
+    create_projection_matrix(fov, WIDTH as f32 / HEIGHT as f32, 0.1, 100.0)
+}
+
+// Everything drawn around the bodies themselves: the sky, the lensing around
+// black holes and the glare of the stars
+struct Effects {
+    skybox: Skybox,
+    corona: Corona,
+    lens_flare: LensFlare,
+    lens: GravitationalLens,
+}
+
+impl Effects {
+    fn new(skybox: Skybox) -> Self {
+        Effects {
+            skybox,
+            corona: Corona::new(),
+            lens_flare: LensFlare::new(),
+            lens: GravitationalLens::new(),
+        }
+    }
+
+    fn render_world(&mut self, scene: &scene::Scene, framebuffer: &mut Framebuffer, uniforms: &mut Uniforms) {
+        framebuffer.clear();
+
+        self.skybox.render(framebuffer, uniforms);
+
+        scene.render(framebuffer, uniforms);
+
+        for hole in scene.black_holes() {
+            self.lens.render(&hole.position, hole.schwarzschild_radius(), framebuffer, uniforms);
+        }
+    }
+
+    fn render_glare(&mut self, scene: &scene::Scene, sun_id: scene::NodeId, framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
+        for star in scene.stars() {
+            self.corona.render(star, framebuffer, uniforms);
+        }
+        let sun = scene.star(sun_id).expect("the scene always has a sun");
+        self.lens_flare.render(&sun.position, sun.radius, sun.light_color(), framebuffer, uniforms);
+    }
+}
+
+// Renders a camera path to numbered PNG frames without opening a window. The
+// simulation runs in the same fixed steps as the viewer, so the same scene,
+// date and path always give the same frames.
+fn render_path(options: &Options, path: &CameraPath) -> Result<(), String> {
+    let StarSystem { mut scene, mut camera, skybox_stars } = load_system(options, options.seed.unwrap_or_default());
+    let sun_id = scene.primary_star().expect("every scene has a star");
+    if let Some(seconds) = options.date {
+        scene.time = seconds;
+        scene.update(0.0);
+    }
+
+    std::fs::create_dir_all(&options.output).map_err(|e| format!("Cannot create {}: {}", options.output, e))?;
+
+    let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+    let mut effects = Effects::new(Skybox::with_seed(skybox_stars, RENDER_SEED));
+    let mut uniforms = Uniforms::new();
+    uniforms.viewport_matrix = create_viewport_matrix(WIDTH as f32, HEIGHT as f32);
+
+    let clock = SimulationClock::new(1.0 / 60.0);
+    let start = scene.time;
+    let frames = (path.duration() * path.fps).ceil() as usize + 1;
+    for frame in 0..frames {
+        let time = frame as f32 / path.fps;
+        // Catch the simulation up with the path in whole steps
+        let target = start + time as f64 * path.time_scale;
+        let step = clock.fixed_step * path.time_scale;
+        while scene.time + step * 0.5 < target {
+            scene.update(step);
+        }
+
+        let pose = path.sample(time);
+        camera.look_at(pose.position, pose.target);
+        uniforms.projection_matrix = projection(pose.fov);
+        uniforms.view_matrix = camera.get_view_matrix();
+        uniforms.camera_position = camera.eye;
+        uniforms.time = clock::shader_time(scene.time);
+        uniforms.lights = scene.lights();
+
+        effects.render_world(&scene, &mut framebuffer, &mut uniforms);
+        effects.render_glare(&scene, sun_id, &mut framebuffer, &uniforms);
+
+        let file = format!("{}/frame_{:05}.png", options.output, frame);
+        framebuffer.save_png(&file)?;
+    }
+    println!("Rendered {} frames to {}", frames, options.output);
+    Ok(())
+}
+
+fn print_eclipses(scene: &scene::Scene, dated: bool) {
+    let events = eclipses::predict(scene, scene.time, eclipses::default_horizon(scene));
+    if events.is_empty() {
+        println!("No eclipses or transits coming up");
+        return;
+    }
+    println!("Upcoming eclipses and transits:");
+    for event in events.iter().take(MAX_LISTED_ECLIPSES) {
+        println!(
+            "  {} to {} (peak {}): {}",
+            timestamp(event.start, dated),
+            timestamp(event.end, dated),
+            timestamp(event.peak, dated),
+            event.pair.describe(scene, event.kind)
+        );
+    }
+}
+
+fn timestamp(seconds: f64, dated: bool) -> String {
+    if dated {
+        ephemeris::format_date(seconds)
+    } else {
+        format!("t = {:.1}s", seconds)
+    }
+}
+
+struct Options {
+    scene: String,
+    // Set when the real solar system was requested
+    scale: Option<ScaleCompression>,
+    date: Option<f64>,
+    // Set when a generated system was requested
+    seed: Option<u64>,
+    // Camera path to play in the viewer, or to render with --render-path
+    camera_path: Option<String>,
+    render_path: bool,
+    // Folder the rendered frames are written to
+    output: String,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            scene: DEFAULT_SCENE.to_string(),
+            scale: None,
+            date: None,
+            seed: None,
+            camera_path: None,
+            render_path: false,
+            output: DEFAULT_OUTPUT.to_string(),
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--scene" => {
+                    options.scene = args.next().ok_or("--scene needs a path to a .toml file")?;
+                }
+                "--solar-system" => {
+                    options.scale.get_or_insert_with(ScaleCompression::compressed);
+                }
+                "--scale" => {
+                    let name = args.next().ok_or("--scale needs 'true' or 'compressed'")?;
+                    options.scale = Some(ScaleCompression::from_name(&name)?);
+                }
+                "--date" => {
+                    let date = args.next().ok_or("--date needs a YYYY-MM-DD date")?;
+                    options.date = Some(ephemeris::seconds_since_j2000(&date)?);
+                }
+                "--seed" => {
+                    let seed = args.next().ok_or("--seed needs a number")?;
+                    options.seed = Some(seed.parse().map_err(|_| format!("Invalid seed '{}'", seed))?);
+                }
+                "--random" => {
+                    options.seed = Some(generator::random_seed());
+                }
+                "--camera-path" => {
+                    options.camera_path = Some(args.next().ok_or("--camera-path needs a path to a .toml file")?);
+                }
+                "--render-path" => {
+                    options.camera_path = Some(args.next().ok_or("--render-path needs a path to a .toml file")?);
+                    options.render_path = true;
+                }
+                "--output" => {
+                    options.output = args.next().ok_or("--output needs a folder")?;
+                }
+                other => return Err(format!("Unknown argument '{}'", other)),
+            }
+        }
+
+        Ok(options)
+    }
+}
@@ -0,0 +1,115 @@
+use crate::camera::{Camera, look_rotation};
+use crate::scene::{Body, NodeId, Scene};
+use crate::shaders::planet_shaders::smoothstep;
+use nalgebra_glm::{Quat, Vec3};
+
+// Closest the camera may get, in radii of the body, so it never clips into it
+const MIN_DISTANCE: f32 = 1.5;
+// Where the camera settles after picking a body, in radii
+pub const DEFAULT_DISTANCE: f32 = 5.0;
+const MAX_DISTANCE: f32 = 90.0;
+// Fraction of the distance one scroll wheel step zooms
+const ZOOM_STEP: f32 = 0.1;
+// Seconds of real time the camera takes to glide to a new target
+const TRANSITION_TIME: f32 = 1.2;
+
+// Bodies worth following: stars and planets, moons included
+pub fn targets(scene: &Scene) -> Vec<NodeId> {
+    scene
+        .nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, node)| matches!(node.body, Body::Star(_) | Body::Planet(_)))
+        .map(|(id, _)| id)
+        .collect()
+}
+
+// Keeps the camera looking at one body: it is carried along the body's orbit,
+// swings around it with the mouse and zooms with the scroll wheel
+pub struct OrbitCamera {
+    pub target: NodeId,
+    pub distance: f32,
+    // Where the target was last frame
+    pivot: Vec3,
+    transition: Option<Transition>,
+}
+
+// Glide from wherever the camera was to the view of a new target
+struct Transition {
+    eye: Vec3,
+    orientation: Quat,
+    elapsed: f32,
+}
+
+impl OrbitCamera {
+    pub fn new(scene: &Scene, target: NodeId, camera: &Camera) -> Self {
+        let radius = scene.nodes[target].body.radius();
+        OrbitCamera {
+            target,
+            distance: radius * DEFAULT_DISTANCE,
+            pivot: scene.nodes[target].world_position,
+            transition: Some(Transition {
+                eye: camera.eye,
+                orientation: camera.orientation,
+                elapsed: 0.0,
+            }),
+        }
+    }
+
+    // Swings around the body, in radians; ignored while still gliding over
+    pub fn rotate(&mut self, camera: &mut Camera, delta_yaw: f32, delta_pitch: f32) {
+        if self.transition.is_none() {
+            camera.orbit(&self.pivot, delta_yaw, delta_pitch);
+        }
+    }
+
+    // Positive steps move in
+    pub fn zoom(&mut self, scene: &Scene, steps: f32) {
+        let radius = scene.nodes[self.target].body.radius();
+        self.distance = (self.distance * (1.0 - ZOOM_STEP).powf(steps)).clamp(radius * MIN_DISTANCE, radius * MAX_DISTANCE);
+    }
+
+    pub fn update(&mut self, scene: &Scene, camera: &mut Camera, real_delta: f32) {
+        // Ride along with the body so its motion doesn't turn into a swing
+        let pivot = scene.nodes[self.target].world_position;
+        let moved = pivot - self.pivot;
+        self.pivot = pivot;
+
+        let Some(transition) = &mut self.transition else {
+            camera.eye += moved;
+            // Keep the direction the body is seen from, at the chosen distance
+            let (eye, orientation) = framing(pivot, camera.eye, self.distance, &camera.up(), camera);
+            camera.eye = eye;
+            camera.orientation = orientation;
+            return;
+        };
+
+        // The glide starts where the camera was, carried along with the body,
+        // and ends looking at it from that side
+        transition.eye += moved;
+        transition.elapsed += real_delta;
+        let up = nalgebra_glm::quat_rotate_vec3(&transition.orientation, &Vec3::y());
+        let (eye, mut orientation) = framing(pivot, transition.eye, self.distance, &up, camera);
+        // q and -q are the same rotation; take the short way round
+        if transition.orientation.dot(&orientation) < 0.0 {
+            orientation = -orientation;
+        }
+
+        let t = smoothstep(0.0, TRANSITION_TIME, transition.elapsed);
+        camera.eye = transition.eye + (eye - transition.eye) * t;
+        camera.orientation = nalgebra_glm::quat_slerp(&transition.orientation, &orientation, t);
+        if t >= 1.0 {
+            self.transition = None;
+        }
+    }
+}
+
+// Eye and orientation looking at `pivot` from `distance` away, on the side `from` is on
+fn framing(pivot: Vec3, from: Vec3, distance: f32, up: &Vec3, camera: &Camera) -> (Vec3, Quat) {
+    let mut away = from - pivot;
+    if away.magnitude() < 1e-6 {
+        away = -camera.forward();
+    }
+    let eye = pivot + away.normalize() * distance;
+    (eye, look_rotation(&(pivot - eye), up))
+}
@@ -0,0 +1,173 @@
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::uniforms::Uniforms;
+use nalgebra_glm::Vec3;
+
+// Caps the work per particle when a long span is integrated in one go
+const MAX_SUBSTEPS: usize = 200;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Blend {
+    // Solid squares that write depth, like the warp streaks
+    Opaque,
+    // Soft glowing sprites that fade out with age
+    Additive,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Particle {
+    pub position: Vec3,
+    pub velocity: Vec3,
+    pub age: f32,
+    pub lifetime: f32,
+    pub color: Color,
+    // Radius in world units
+    pub size: f32,
+    // Fraction of the system's central gravity this particle feels
+    pub gravity: f32,
+}
+
+impl Particle {
+    pub fn new(position: Vec3, velocity: Vec3, lifetime: f32, color: Color, size: f32) -> Self {
+        Particle {
+            position,
+            velocity,
+            age: 0.0,
+            lifetime,
+            color,
+            size,
+            gravity: 0.0,
+        }
+    }
+
+    pub fn alive(&self) -> bool {
+        self.age >= 0.0 && self.age <= self.lifetime
+    }
+
+    // Semi-implicit Euler, substepped so curved paths stay smooth; a negative
+    // delta runs the particle backwards
+    fn advance(&mut self, delta_time: f32, center: &Vec3, gm: f32, max_step: f32) {
+        let substeps = if self.gravity != 0.0 && gm > 0.0 {
+            ((delta_time.abs() / max_step).ceil() as usize).clamp(1, MAX_SUBSTEPS)
+        } else {
+            1
+        };
+        let h = delta_time / substeps as f32;
+
+        for _ in 0..substeps {
+            if self.gravity != 0.0 && gm > 0.0 {
+                let offset = center - self.position;
+                let distance2 = offset.norm_squared().max(1e-4);
+                self.velocity += offset * (self.gravity * gm / (distance2 * distance2.sqrt()) * h);
+            }
+            self.position += self.velocity * h;
+        }
+        self.age += delta_time;
+    }
+}
+
+pub struct ParticleSystem {
+    pub particles: Vec<Particle>,
+    pub blend: Blend,
+    // Optional point mass the particles fall towards: position and G * mass
+    pub attractor: Option<(Vec3, f32)>,
+    // Longest integration step for particles that feel the attractor
+    pub max_step: f32,
+}
+
+impl ParticleSystem {
+    pub fn new(blend: Blend) -> Self {
+        ParticleSystem {
+            particles: Vec::new(),
+            blend,
+            attractor: None,
+            max_step: 0.01,
+        }
+    }
+
+    // Adds a particle that was emitted `age` seconds ago, moving it to where it is now
+    pub fn emit(&mut self, mut particle: Particle, age: f32) {
+        if age != 0.0 {
+            let (center, gm) = self.attractor.unwrap_or((Vec3::zeros(), 0.0));
+            particle.advance(age, &center, gm, self.max_step);
+        }
+        if particle.alive() {
+            self.particles.push(particle);
+        }
+    }
+
+    pub fn update(&mut self, delta_time: f32) {
+        let (center, gm) = self.attractor.unwrap_or((Vec3::zeros(), 0.0));
+        let max_step = self.max_step;
+        self.particles.retain_mut(|p| {
+            p.advance(delta_time, &center, gm, max_step);
+            p.alive()
+        });
+    }
+
+    pub fn clear(&mut self) {
+        self.particles.clear();
+    }
+
+    // Particle positions are relative to `origin`, so a system can live in a moving frame
+    pub fn render(&self, origin: &Vec3, framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
+        let right = uniforms.camera_right();
+
+        for particle in &self.particles {
+            let position = origin + particle.position;
+            let Some(center) = uniforms.world_to_screen(&position) else {
+                continue;
+            };
+            let Some(edge) = uniforms.world_to_screen(&(position + right * particle.size)) else {
+                continue;
+            };
+            if center.z < -1.0 || center.z > 1.0 {
+                continue;
+            }
+            let radius = (edge.xy() - center.xy()).norm().clamp(0.5, 24.0);
+
+            match self.blend {
+                Blend::Opaque => draw_square(framebuffer, &center, radius, &particle.color),
+                Blend::Additive => {
+                    let fade = 1.0 - particle.age / particle.lifetime;
+                    glow(framebuffer, &center, radius, &(particle.color * fade));
+                }
+            }
+        }
+    }
+}
+
+fn draw_square(framebuffer: &mut Framebuffer, center: &Vec3, radius: f32, color: &Color) {
+    let size = (radius * 2.0).ceil() as i32;
+    let x0 = (center.x - radius) as i32;
+    let y0 = (center.y - radius) as i32;
+
+    for dy in 0..size {
+        for dx in 0..size {
+            let (x, y) = (x0 + dx, y0 + dy);
+            if x >= 0 && y >= 0 {
+                framebuffer.point_with_depth(x as usize, y as usize, center.z, color);
+            }
+        }
+    }
+}
+
+// Additive disc with a quadratic falloff, `center` being screen x, y and depth
+pub fn glow(framebuffer: &mut Framebuffer, center: &Vec3, radius: f32, color: &Color) {
+    let reach = radius.ceil() as i32 + 1;
+    let (cx, cy) = (center.x as i32, center.y as i32);
+
+    for y in (cy - reach)..=(cy + reach) {
+        for x in (cx - reach)..=(cx + reach) {
+            if x < 0 || y < 0 {
+                continue;
+            }
+            let dx = x as f32 + 0.5 - center.x;
+            let dy = y as f32 + 0.5 - center.y;
+            let falloff = 1.0 - (dx * dx + dy * dy).sqrt() / radius.max(0.75);
+            if falloff > 0.0 {
+                framebuffer.blend_add(x as usize, y as usize, center.z, &(*color * (falloff * falloff)));
+            }
+        }
+    }
+}
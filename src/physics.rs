@@ -0,0 +1,223 @@
+use crate::scene::NodeId;
+use nalgebra_glm::DVec3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Integrator {
+    // Kick-drift-kick; symplectic, so energy errors oscillate instead of growing
+    Leapfrog,
+    // Classic fourth order Runge-Kutta; more accurate per step but drifts over time
+    Rk4,
+}
+
+impl Integrator {
+    pub fn name(self) -> &'static str {
+        match self {
+            Integrator::Leapfrog => "leapfrog",
+            Integrator::Rk4 => "RK4",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Integrator::Leapfrog => Integrator::Rk4,
+            Integrator::Rk4 => Integrator::Leapfrog,
+        }
+    }
+}
+
+pub struct PointMass {
+    pub node: NodeId,
+    // Gravitational parameter (G * mass); zero for test particles
+    pub mass: f64,
+    pub position: DVec3,
+    pub velocity: DVec3,
+}
+
+pub struct NBody {
+    pub bodies: Vec<PointMass>,
+    pub integrator: Integrator,
+    // Plummer softening length, keeps close encounters from blowing up
+    pub softening: f64,
+    pub max_step: f64,
+    // Caps the work per call; callers keep each call within max_time_step
+    // so the step never has to grow past max_step
+    pub max_substeps: usize,
+    pub time: f64,
+    initial_energy: f64,
+    // Accelerations at the current positions, left by the last leapfrog
+    // substep so the next one doesn't compute them again
+    accelerations: Option<Vec<DVec3>>,
+}
+
+impl NBody {
+    pub fn new(mut bodies: Vec<PointMass>, integrator: Integrator) -> Self {
+        // Work in the centre-of-mass frame so the whole system doesn't drift away
+        let total_mass: f64 = bodies.iter().map(|b| b.mass).sum();
+        if total_mass > 0.0 {
+            let momentum: DVec3 = bodies.iter().map(|b| b.velocity * b.mass).sum();
+            let center: DVec3 = bodies.iter().map(|b| b.position * b.mass).sum();
+            for body in &mut bodies {
+                body.velocity -= momentum / total_mass;
+                body.position -= center / total_mass;
+            }
+        }
+
+        let mut nbody = NBody {
+            bodies,
+            integrator,
+            softening: 0.05,
+            max_step: 0.002,
+            max_substeps: 4000,
+            time: 0.0,
+            initial_energy: 0.0,
+            accelerations: None,
+        };
+        nbody.initial_energy = nbody.total_energy();
+        nbody
+    }
+
+    // Longest call to `step` that still integrates at max_step
+    pub fn max_time_step(&self) -> f64 {
+        self.max_step * self.max_substeps as f64
+    }
+
+    fn accelerations(&self, positions: &[DVec3]) -> Vec<DVec3> {
+        let eps2 = self.softening * self.softening;
+        let mut accelerations = vec![DVec3::zeros(); positions.len()];
+
+        for i in 0..positions.len() {
+            for j in (i + 1)..positions.len() {
+                let offset = positions[j] - positions[i];
+                let distance2 = offset.norm_squared() + eps2;
+                let inv_r3 = 1.0 / (distance2 * distance2.sqrt());
+
+                accelerations[i] += offset * (self.bodies[j].mass * inv_r3);
+                accelerations[j] -= offset * (self.bodies[i].mass * inv_r3);
+            }
+        }
+
+        accelerations
+    }
+
+    pub fn step(&mut self, delta_time: f64) {
+        let substeps = ((delta_time.abs() / self.max_step).ceil() as usize).clamp(1, self.max_substeps);
+        let h = delta_time / substeps as f64;
+
+        for _ in 0..substeps {
+            match self.integrator {
+                Integrator::Leapfrog => self.leapfrog_step(h),
+                Integrator::Rk4 => self.rk4_step(h),
+            }
+        }
+        self.time += delta_time;
+    }
+
+    fn leapfrog_step(&mut self, h: f64) {
+        let accelerations = match self.accelerations.take() {
+            Some(accelerations) => accelerations,
+            None => {
+                let positions: Vec<DVec3> = self.bodies.iter().map(|b| b.position).collect();
+                self.accelerations(&positions)
+            }
+        };
+        for (body, acceleration) in self.bodies.iter_mut().zip(&accelerations) {
+            body.velocity += acceleration * (h * 0.5);
+            body.position += body.velocity * h;
+        }
+
+        let positions: Vec<DVec3> = self.bodies.iter().map(|b| b.position).collect();
+        let accelerations = self.accelerations(&positions);
+        for (body, acceleration) in self.bodies.iter_mut().zip(&accelerations) {
+            body.velocity += acceleration * (h * 0.5);
+        }
+        self.accelerations = Some(accelerations);
+    }
+
+    fn rk4_step(&mut self, h: f64) {
+        let p0: Vec<DVec3> = self.bodies.iter().map(|b| b.position).collect();
+        let v0: Vec<DVec3> = self.bodies.iter().map(|b| b.velocity).collect();
+
+        // Evaluates the derivative (velocity, acceleration) at p0 + dp * scale, v0 + dv * scale
+        let derivative = |dp: &[DVec3], dv: &[DVec3], scale: f64| {
+            let p: Vec<DVec3> = p0.iter().zip(dp).map(|(p, d)| p + d * scale).collect();
+            let v: Vec<DVec3> = v0.iter().zip(dv).map(|(v, d)| v + d * scale).collect();
+            let a = self.accelerations(&p);
+            (v, a)
+        };
+
+        let zeros = vec![DVec3::zeros(); p0.len()];
+        let (k1p, k1v) = derivative(&zeros, &zeros, 0.0);
+        let (k2p, k2v) = derivative(&k1p, &k1v, h * 0.5);
+        let (k3p, k3v) = derivative(&k2p, &k2v, h * 0.5);
+        let (k4p, k4v) = derivative(&k3p, &k3v, h);
+
+        self.accelerations = None;
+        for (i, body) in self.bodies.iter_mut().enumerate() {
+            body.position = p0[i] + (k1p[i] + k2p[i] * 2.0 + k3p[i] * 2.0 + k4p[i]) * (h / 6.0);
+            body.velocity = v0[i] + (k1v[i] + k2v[i] * 2.0 + k3v[i] * 2.0 + k4v[i]) * (h / 6.0);
+        }
+    }
+
+    // Kinetic plus potential energy, scaled by G like the masses
+    pub fn total_energy(&self) -> f64 {
+        let eps2 = self.softening * self.softening;
+        let mut energy = 0.0;
+
+        for (i, a) in self.bodies.iter().enumerate() {
+            energy += 0.5 * a.mass * a.velocity.norm_squared();
+            for b in &self.bodies[i + 1..] {
+                let distance = ((b.position - a.position).norm_squared() + eps2).sqrt();
+                energy -= a.mass * b.mass / distance;
+            }
+        }
+
+        energy
+    }
+
+    // Relative change in total energy since the simulation started
+    pub fn energy_drift(&self) -> f64 {
+        if self.initial_energy == 0.0 {
+            return 0.0;
+        }
+        (self.total_energy() - self.initial_energy) / self.initial_energy.abs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A light planet on a circular orbit of radius 4 around a star
+    fn circular_pair(integrator: Integrator) -> NBody {
+        let (star, planet, radius): (f64, f64, f64) = (40.96, 0.04, 4.0);
+        let speed = ((star + planet) / radius).sqrt();
+        let bodies = vec![
+            PointMass { node: 0, mass: star, position: DVec3::zeros(), velocity: DVec3::zeros() },
+            PointMass { node: 1, mass: planet, position: DVec3::new(radius, 0.0, 0.0), velocity: DVec3::new(0.0, 0.0, -speed) },
+        ];
+        NBody::new(bodies, integrator)
+    }
+
+    fn worst_drift(integrator: Integrator) -> f64 {
+        let mut nbody = circular_pair(integrator);
+        let mut worst = 0.0f64;
+        // Ten orbits, checked a few times each
+        for _ in 0..80 {
+            nbody.step(1.0);
+            worst = worst.max(nbody.energy_drift().abs());
+        }
+        worst
+    }
+
+    #[test]
+    fn leapfrog_keeps_energy_bounded() {
+        let drift = worst_drift(Integrator::Leapfrog);
+        assert!(drift < 1e-8, "drift {}", drift);
+    }
+
+    #[test]
+    fn rk4_keeps_energy_bounded() {
+        let drift = worst_drift(Integrator::Rk4);
+        assert!(drift < 1e-8, "drift {}", drift);
+    }
+}
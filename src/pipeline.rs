@@ -2,55 +2,35 @@ use crate::color::Color;
 use crate::framebuffer::Framebuffer;
 use crate::vertex::{Vertex2D, Vertex};
 use crate::fragment::Fragment;
+use crate::geometry::Mesh;
 use crate::uniforms::Uniforms;
 use crate::shaders::vertex_shader::vertex_shader;
 use crate::shaders::fragment_shader::fragment_shader;
+use crate::shaders::ring_shader::ring_shader;
+use crate::shaders::disk_shader::disk_shader;
+use crate::shaders::lighting::incoming;
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+// Instances smaller than this on screen, in pixels of radius, are drawn as a single point
+const SPLAT_RADIUS: f32 = 1.5;
 
 fn edge_function(a: &Vertex2D, b: &Vertex2D, c: &Vertex2D) -> f32 {
     (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
 }
 
-pub fn triangle(v1: &Vertex2D, v2: &Vertex2D, v3: &Vertex2D, framebuffer: &mut Framebuffer) {
-    let min_x = v1.x.min(v2.x).min(v3.x).max(0.0) as usize;
-    let min_y = v1.y.min(v2.y).min(v3.y).max(0.0) as usize;
-    let max_x = v1.x.max(v2.x).max(v3.x).min(framebuffer.width as f32 - 1.0) as usize;
-    let max_y = v1.y.max(v2.y).max(v3.y).min(framebuffer.height as f32 - 1.0) as usize;
-
-    let area = edge_function(v1, v2, v3);
-    
-    if area.abs() < 0.0001 {
-        return;
-    }
-
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            let p = Vertex2D::new(x as f32 + 0.5, y as f32 + 0.5, Color::BLACK);
-
-            let w1 = edge_function(v2, v3, &p);
-            let w2 = edge_function(v3, v1, &p);
-            let w3 = edge_function(v1, v2, &p);
-
-            if w1 >= 0.0 && w2 >= 0.0 && w3 >= 0.0 {
-                let w1 = w1 / area;
-                let w2 = w2 / area;
-                let w3 = w3 / area;
-
-                let r = (v1.color.r as f32 * w1 + v2.color.r as f32 * w2 + v3.color.r as f32 * w3) as u8;
-                let g = (v1.color.g as f32 * w1 + v2.color.g as f32 * w2 + v3.color.g as f32 * w3) as u8;
-                let b = (v1.color.b as f32 * w1 + v2.color.b as f32 * w2 + v3.color.b as f32 * w3) as u8;
-
-                let color = Color::new(r, g, b);
-                framebuffer.point(x, y, &color);
-            }
-        }
-    }
-}
-
 pub fn triangle_3d(v1: &Vertex, v2: &Vertex, v3: &Vertex, uniforms: &Uniforms, framebuffer: &mut Framebuffer) {
     let vt1 = vertex_shader(v1, uniforms);
     let vt2 = vertex_shader(v2, uniforms);
     let vt3 = vertex_shader(v3, uniforms);
 
+    // No clipping against the near plane: drop triangles that cross it
+    for vt in [&vt1, &vt2, &vt3] {
+        let clip = vt.transformed_position;
+        if clip.w <= 1e-4 || clip.z < -clip.w {
+            return;
+        }
+    }
+
     let ndc1 = vt1.transformed_position / vt1.transformed_position.w;
     let ndc2 = vt2.transformed_position / vt2.transformed_position.w;
     let ndc3 = vt3.transformed_position / vt3.transformed_position.w;
@@ -59,9 +39,9 @@ pub fn triangle_3d(v1: &Vertex, v2: &Vertex, v3: &Vertex, uniforms: &Uniforms, f
     let screen2 = uniforms.viewport_matrix * ndc2;
     let screen3 = uniforms.viewport_matrix * ndc3;
 
-    let v1_screen = Vertex2D::new(screen1.x, screen1.y, vt1.color);
-    let v2_screen = Vertex2D::new(screen2.x, screen2.y, vt2.color);
-    let v3_screen = Vertex2D::new(screen3.x, screen3.y, vt3.color);
+    let v1_screen = Vertex2D::new(screen1.x, screen1.y);
+    let v2_screen = Vertex2D::new(screen2.x, screen2.y);
+    let v3_screen = Vertex2D::new(screen3.x, screen3.y);
 
     let min_x = v1_screen.x.min(v2_screen.x).min(v3_screen.x).max(0.0) as usize;
     let min_y = v1_screen.y.min(v2_screen.y).min(v3_screen.y).max(0.0) as usize;
@@ -76,7 +56,7 @@ pub fn triangle_3d(v1: &Vertex, v2: &Vertex, v3: &Vertex, uniforms: &Uniforms, f
 
     for y in min_y..=max_y {
         for x in min_x..=max_x {
-            let p = Vertex2D::new(x as f32 + 0.5, y as f32 + 0.5, Color::BLACK);
+            let p = Vertex2D::new(x as f32 + 0.5, y as f32 + 0.5);
 
             let w1 = edge_function(&v2_screen, &v3_screen, &p);
             let w2 = edge_function(&v3_screen, &v1_screen, &p);
@@ -97,6 +77,8 @@ pub fn triangle_3d(v1: &Vertex, v2: &Vertex, v3: &Vertex, uniforms: &Uniforms, f
                 let depth = ndc1.z * w1 + ndc2.z * w2 + ndc3.z * w3;
 
                 let normal = (vt1.transformed_normal * w1 + vt2.transformed_normal * w2 + vt3.transformed_normal * w3).normalize();
+                let vertex_position = vt1.position * w1 + vt2.position * w2 + vt3.position * w3;
+                let world_position = vt1.world_position * w1 + vt2.world_position * w2 + vt3.world_position * w3;
 
                 let r = (vt1.color.r as f32 * w1 + vt2.color.r as f32 * w2 + vt3.color.r as f32 * w3) as u8;
                 let g = (vt1.color.g as f32 * w1 + vt2.color.g as f32 * w2 + vt3.color.g as f32 * w3) as u8;
@@ -104,9 +86,18 @@ pub fn triangle_3d(v1: &Vertex, v2: &Vertex, v3: &Vertex, uniforms: &Uniforms, f
 
                 let color = Color::new(r, g, b);
 
-                let mut fragment = Fragment::new(x, y, depth);
-                fragment.color = color;
-                fragment.normal = normal;
+                let fragment = Fragment { color, normal, vertex_position, world_position };
+
+                if let Some(bands) = &uniforms.ring {
+                    let (ring_color, alpha) = ring_shader(&fragment, bands, uniforms);
+                    framebuffer.blend_alpha(x, y, depth, &ring_color, alpha);
+                    continue;
+                }
+                if let Some(disk) = &uniforms.disk {
+                    let (disk_color, alpha) = disk_shader(&fragment, disk, uniforms);
+                    framebuffer.blend_alpha_solid(x, y, depth, &disk_color, alpha);
+                    continue;
+                }
 
                 let shaded_color = fragment_shader(&fragment, uniforms);
                 framebuffer.point_with_depth(x, y, depth, &shaded_color);
@@ -114,3 +105,59 @@ pub fn triangle_3d(v1: &Vertex, v2: &Vertex, v3: &Vertex, uniforms: &Uniforms, f
         }
     }
 }
+
+pub fn render_mesh(mesh: &Mesh, uniforms: &Uniforms, framebuffer: &mut Framebuffer) {
+    for triangle in mesh.vertices.chunks_exact(3) {
+        triangle_3d(&triangle[0], &triangle[1], &triangle[2], uniforms, framebuffer);
+    }
+}
+
+// Draws one shared mesh once per transform, running its vertices through the
+// pipeline again for every copy. Copies that would cover only a pixel or two
+// are splatted as a single lit point instead of rasterized.
+pub fn render_copies(mesh: &Mesh, transforms: &[Mat4], uniforms: &mut Uniforms, framebuffer: &mut Framebuffer) {
+    let Some(first) = mesh.vertices.first() else {
+        return;
+    };
+    let base_color = first.color;
+    let mesh_radius = mesh.vertices.iter().map(|v| v.position.norm()).fold(0.0, f32::max);
+    let right = uniforms.camera_right();
+
+    for transform in transforms {
+        let center = (transform * Vec4::new(0.0, 0.0, 0.0, 1.0)).xyz();
+        let scale = Vec3::new(transform[(0, 0)], transform[(1, 0)], transform[(2, 0)]).norm();
+
+        let Some(screen) = uniforms.world_to_screen(&center) else {
+            continue;
+        };
+        let Some(edge) = uniforms.world_to_screen(&(center + right * mesh_radius * scale)) else {
+            continue;
+        };
+        let pixels = (edge.xy() - screen.xy()).norm();
+
+        let off_screen = screen.x < -pixels
+            || screen.y < -pixels
+            || screen.x > framebuffer.width as f32 + pixels
+            || screen.y > framebuffer.height as f32 + pixels;
+        if off_screen || screen.z < -1.0 {
+            continue;
+        }
+
+        if pixels < SPLAT_RADIUS {
+            if screen.x < 0.0 || screen.y < 0.0 {
+                continue;
+            }
+            // Phase lighting: brightest with the light behind the camera
+            let to_camera = (uniforms.camera_position - center).normalize();
+            let (lights, count) = incoming(&center, uniforms);
+            let color = lights[..count].iter().fold(Color::BLACK, |total, light| {
+                let phase = 0.5 + 0.5 * light.direction.dot(&to_camera);
+                total + base_color * light.color * (light.strength * (0.3 + 0.7 * phase * light.shadow))
+            });
+            framebuffer.point_with_depth(screen.x as usize, screen.y as usize, screen.z, &color);
+        } else {
+            uniforms.model_matrix = *transform;
+            render_mesh(mesh, uniforms, framebuffer);
+        }
+    }
+}
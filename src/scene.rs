@@ -0,0 +1,571 @@
+use crate::celestial::{AsteroidBelt, BlackHole, Comet, OrbitalElements, Planet, Star};
+use crate::framebuffer::Framebuffer;
+use crate::physics::{Integrator, NBody, PointMass};
+use crate::pipeline::{render_copies, render_mesh};
+use crate::uniforms::{Light, Occluder, Uniforms};
+use nalgebra_glm::{DVec3, Mat4, Vec3};
+
+// Share of its Hill radius within which a body holds on to its satellites
+pub const HILL_STABLE_FRACTION: f64 = 1.0 / 3.0;
+
+pub type NodeId = usize;
+
+pub enum Body {
+    // Massless pivot, e.g. the common centre two stars orbit around
+    Barycenter,
+    Star(Star),
+    Planet(Planet),
+    // Swarm of small rocks orbiting the parent, not part of the n-body simulation
+    Belt(AsteroidBelt),
+    // Massless, and kept on its orbit even under n-body physics so its tails stay smooth
+    Comet(Comet),
+    // Massive and dark; its disk glows and the lensing pass draws its shadow
+    BlackHole(BlackHole),
+}
+
+impl Body {
+    fn update(&mut self, delta_time: f32) {
+        match self {
+            Body::Barycenter | Body::Belt(_) => {}
+            Body::Star(star) => star.update(delta_time),
+            Body::Planet(planet) => planet.update(delta_time),
+            Body::Comet(comet) => comet.update(delta_time),
+            Body::BlackHole(hole) => hole.update(delta_time),
+        }
+    }
+
+    fn set_position(&mut self, position: Vec3) {
+        match self {
+            Body::Barycenter | Body::Belt(_) => {}
+            Body::Star(star) => star.position = position,
+            Body::Planet(planet) => planet.position = position,
+            Body::Comet(comet) => comet.position = position,
+            Body::BlackHole(hole) => hole.position = position,
+        }
+    }
+
+    fn spin_matrix(&self) -> Mat4 {
+        match self {
+            Body::Barycenter | Body::Belt(_) => Mat4::identity(),
+            Body::Star(star) => star.spin.matrix(),
+            Body::Planet(planet) => planet.spin.matrix(),
+            Body::Comet(comet) => comet.spin.matrix(),
+            Body::BlackHole(hole) => hole.spin.matrix(),
+        }
+    }
+
+    pub fn mass(&self) -> f64 {
+        match self {
+            Body::Barycenter | Body::Belt(_) => 0.0,
+            Body::Star(star) => star.mass,
+            Body::Planet(planet) => planet.mass,
+            Body::Comet(_) => 0.0,
+            Body::BlackHole(hole) => hole.mass,
+        }
+    }
+
+    // Whether the n-body simulation moves this body, rather than its orbit
+    fn simulated(&self) -> bool {
+        !matches!(self, Body::Belt(_) | Body::Comet(_))
+    }
+
+    pub fn radius(&self) -> f32 {
+        match self {
+            Body::Barycenter | Body::Belt(_) => 0.0,
+            Body::Star(star) => star.radius,
+            Body::Planet(planet) => planet.radius,
+            Body::Comet(comet) => comet.radius,
+            Body::BlackHole(hole) => hole.schwarzschild_radius(),
+        }
+    }
+}
+
+pub struct SceneNode {
+    pub name: String,
+    pub parent: Option<NodeId>,
+    pub body: Body,
+    // Motion around the parent; nodes without an orbit sit at a fixed offset
+    pub orbit: Option<OrbitalElements>,
+    pub offset: Vec3,
+    pub world_position: Vec3,
+    // Translation-only frame that children are placed in, so they don't
+    // inherit the parent's spin
+    pub frame: Mat4,
+    pub world_matrix: Mat4,
+}
+
+pub struct Scene {
+    pub nodes: Vec<SceneNode>,
+    pub time: f64,
+    // When set, bodies follow Newtonian gravity instead of their orbits
+    pub physics: Option<NBody>,
+    order: Vec<NodeId>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Scene {
+            nodes: Vec::new(),
+            time: 0.0,
+            physics: None,
+            order: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, name: &str, body: Body, parent: Option<NodeId>, orbit: Option<OrbitalElements>) -> Result<NodeId, String> {
+        let id = self.nodes.len();
+        self.nodes.push(SceneNode {
+            name: name.to_string(),
+            parent: None,
+            body,
+            orbit,
+            offset: Vec3::zeros(),
+            world_position: Vec3::zeros(),
+            frame: Mat4::identity(),
+            world_matrix: Mat4::identity(),
+        });
+        if let Err(e) = self.set_parent(id, parent) {
+            self.nodes.pop();
+            return Err(e);
+        }
+        self.place(id);
+        Ok(id)
+    }
+
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> Result<(), String> {
+        if id >= self.nodes.len() {
+            return Err(format!("Unknown scene node {}", id));
+        }
+
+        let mut ancestor = parent;
+        while let Some(a) = ancestor {
+            if a >= self.nodes.len() {
+                return Err(format!("'{}' orbits unknown scene node {}", self.nodes[id].name, a));
+            }
+            if a == id {
+                return Err(format!("'{}' cannot orbit one of its own moons", self.nodes[id].name));
+            }
+            ancestor = self.nodes[a].parent;
+        }
+
+        self.nodes[id].parent = parent;
+        self.resolve_order();
+        Ok(())
+    }
+
+    pub fn mass(&self, id: Option<NodeId>) -> f64 {
+        id.and_then(|id| self.nodes.get(id))
+            .map(|node| node.body.mass())
+            .unwrap_or(0.0)
+    }
+
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes.iter().position(|node| node.name == name)
+    }
+
+    // The star lighting the scene: the first one added
+    pub fn primary_star(&self) -> Option<NodeId> {
+        self.nodes.iter().position(|node| matches!(node.body, Body::Star(_)))
+    }
+
+    pub fn stars(&self) -> impl Iterator<Item = &Star> {
+        self.nodes.iter().filter_map(|node| match &node.body {
+            Body::Star(star) => Some(star),
+            _ => None,
+        })
+    }
+
+    // The star that shines brightest on `point`
+    pub fn brightest_star(&self, point: &Vec3) -> Option<&Star> {
+        self.stars()
+            .map(|star| (star, star.luminosity() / (star.position - point).norm_squared().max(1e-6)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(star, _)| star)
+    }
+
+    pub fn black_holes(&self) -> impl Iterator<Item = &BlackHole> {
+        self.nodes.iter().filter_map(|node| match &node.body {
+            Body::BlackHole(hole) => Some(hole),
+            _ => None,
+        })
+    }
+
+    // Every star, as the shaders see it
+    pub fn lights(&self) -> Vec<Light> {
+        self.stars()
+            .map(|star| Light {
+                position: star.position,
+                color: star.light_color(),
+                radius: star.radius,
+                luminosity: star.luminosity(),
+            })
+            .collect()
+    }
+
+    // Mass of a body together with everything orbiting it
+    pub fn system_mass(&self, id: NodeId) -> f64 {
+        self.nodes[id].body.mass()
+            + (0..self.nodes.len())
+                .filter(|&child| self.nodes[child].parent == Some(id))
+                .map(|child| self.system_mass(child))
+                .sum::<f64>()
+    }
+
+    // Gravitational parameter that paces an orbit around `parent` for a body
+    // of `own_mass` (its whole system's, moons included). A barycenter has no
+    // mass of its own: a body at distance a from it moves as in a two-body
+    // orbit of size a * M / (M - m), M being everything bound to the barycenter.
+    pub fn orbit_mass(&self, parent: NodeId, own_mass: f64) -> f64 {
+        match self.nodes[parent].body {
+            Body::Barycenter => {
+                let total = self.system_mass(parent);
+                if total <= 0.0 {
+                    return 0.0;
+                }
+                (total - own_mass).max(0.0).powi(3) / (total * total)
+            }
+            _ => self.nodes[parent].body.mass(),
+        }
+    }
+
+    // Reach of a body's own gravity against its parent's tides (the Hill radius)
+    pub fn hill_radius(&self, id: NodeId) -> Option<f64> {
+        let node = &self.nodes[id];
+        let orbit = node.orbit.as_ref()?;
+        let central = self.orbit_mass(node.parent?, self.system_mass(id));
+        let own = node.body.mass();
+        if central <= 0.0 || own <= 0.0 {
+            return None;
+        }
+        Some(orbit.semi_major_axis * (own / (3.0 * central)).cbrt())
+    }
+
+    // Simulated bodies whose orbits reach past the stable part of their
+    // parent's Hill sphere, which n-body gravity will pull away from it
+    pub fn loose_moons(&self) -> Vec<NodeId> {
+        (0..self.nodes.len())
+            .filter(|&id| {
+                let node = &self.nodes[id];
+                let (Some(parent), Some(orbit)) = (node.parent, &node.orbit) else {
+                    return false;
+                };
+                node.body.simulated()
+                    && self
+                        .hill_radius(parent)
+                        .is_some_and(|hill| orbit.semi_major_axis * (1.0 + orbit.eccentricity) > hill * HILL_STABLE_FRACTION)
+            })
+            .collect()
+    }
+
+    pub fn star(&self, id: NodeId) -> Option<&Star> {
+        match &self.nodes.get(id)?.body {
+            Body::Star(star) => Some(star),
+            _ => None,
+        }
+    }
+
+    pub fn star_mut(&mut self, id: NodeId) -> Option<&mut Star> {
+        match &mut self.nodes.get_mut(id)?.body {
+            Body::Star(star) => Some(star),
+            _ => None,
+        }
+    }
+
+    // Parents always come before their children, ordered by depth in the tree
+    fn resolve_order(&mut self) {
+        let depth = |mut id: NodeId| {
+            let mut depth = 0;
+            while let Some(parent) = self.nodes[id].parent {
+                id = parent;
+                depth += 1;
+            }
+            depth
+        };
+
+        let mut order: Vec<NodeId> = (0..self.nodes.len()).collect();
+        order.sort_by_key(|&id| depth(id));
+        self.order = order;
+    }
+
+    fn place(&mut self, id: NodeId) {
+        let parent_frame = self.nodes[id]
+            .parent
+            .map(|parent| self.nodes[parent].frame)
+            .unwrap_or_else(Mat4::identity);
+
+        let time = self.time;
+        let node = &mut self.nodes[id];
+        let local = match &node.orbit {
+            Some(orbit) => orbit.position_at(time) + node.offset,
+            None => node.offset,
+        };
+
+        node.frame = nalgebra_glm::translate(&parent_frame, &local);
+        node.world_position = (node.frame * nalgebra_glm::vec4(0.0, 0.0, 0.0, 1.0)).xyz();
+        node.world_matrix = node.frame * node.body.spin_matrix();
+        node.body.set_position(node.world_position);
+        if let Body::Belt(belt) = &mut node.body {
+            belt.update_transforms(&node.frame, time);
+        }
+    }
+
+    // Acceleration gravity gives a free body at `point`, from everything with
+    // mass. Inside a body only the mass below counts, so the pull fades to
+    // nothing at its centre instead of blowing up.
+    pub fn gravity_at(&self, point: &Vec3) -> Vec3 {
+        let mut acceleration = Vec3::zeros();
+        for node in &self.nodes {
+            let mass = node.body.mass() as f32;
+            if mass <= 0.0 {
+                continue;
+            }
+            let offset = node.world_position - point;
+            let distance = offset.magnitude().max(node.body.radius()).max(1e-6);
+            acceleration += offset * (mass / (distance * distance * distance));
+        }
+        acceleration
+    }
+
+    // World-space position and velocity implied by the orbits at the current time
+    fn kepler_state(&self, id: NodeId) -> (DVec3, DVec3) {
+        self.kepler_state_at(id, self.time)
+    }
+
+    // Where the orbits put a body at any time, ignoring the n-body simulation
+    pub fn kepler_state_at(&self, id: NodeId, time: f64) -> (DVec3, DVec3) {
+        let node = &self.nodes[id];
+        let (mut position, mut velocity) = match &node.orbit {
+            Some(orbit) => orbit.state_at(time),
+            None => (DVec3::zeros(), DVec3::zeros()),
+        };
+        position += DVec3::new(node.offset.x as f64, node.offset.y as f64, node.offset.z as f64);
+
+        if let Some(parent) = node.parent {
+            let (parent_position, parent_velocity) = self.kepler_state_at(parent, time);
+            position += parent_position;
+            velocity += parent_velocity;
+        }
+
+        (position, velocity)
+    }
+
+    // Switches to the n-body simulation, starting from where the orbits put every body
+    pub fn enable_physics(&mut self, integrator: Integrator) {
+        let bodies = (0..self.nodes.len())
+            .filter(|&id| self.nodes[id].body.simulated())
+            .map(|id| {
+                let (position, velocity) = self.kepler_state(id);
+                PointMass {
+                    node: id,
+                    mass: self.nodes[id].body.mass(),
+                    position,
+                    velocity,
+                }
+            })
+            .collect();
+
+        self.physics = Some(NBody::new(bodies, integrator));
+    }
+
+    // Back to the orbits, which pick up from the current scene time
+    pub fn disable_physics(&mut self) {
+        self.physics = None;
+    }
+
+    pub fn update(&mut self, delta_time: f64) {
+        self.time += delta_time;
+
+        if let Some(physics) = &mut self.physics {
+            physics.step(delta_time);
+
+            for body in &physics.bodies {
+                let position = Vec3::new(body.position.x as f32, body.position.y as f32, body.position.z as f32);
+                let node = &mut self.nodes[body.node];
+                node.body.update(delta_time as f32);
+                node.frame = nalgebra_glm::translation(&position);
+                node.world_position = position;
+                node.world_matrix = node.frame * node.body.spin_matrix();
+                node.body.set_position(position);
+            }
+
+            // Belts and comets stay centred on whatever they circle
+            for i in 0..self.order.len() {
+                let id = self.order[i];
+                if !self.nodes[id].body.simulated() {
+                    self.nodes[id].body.update(delta_time as f32);
+                    self.place(id);
+                }
+            }
+        } else {
+            for i in 0..self.order.len() {
+                let id = self.order[i];
+                self.nodes[id].body.update(delta_time as f32);
+                self.place(id);
+            }
+        }
+
+        for id in 0..self.nodes.len() {
+            if !matches!(self.nodes[id].body, Body::Comet(_)) {
+                continue;
+            }
+            // Tails blow away from the star, which needn't be what the comet orbits
+            let origin = self.nodes[id].parent.map_or(Vec3::zeros(), |parent| self.nodes[parent].world_position);
+            let sun = self.brightest_star(&self.nodes[id].world_position).map(|star| star.position - origin);
+            let node = &mut self.nodes[id];
+            if let Body::Comet(comet) = &mut node.body {
+                comet.update_tails(node.orbit.as_ref(), sun, self.time, delta_time);
+            }
+        }
+    }
+
+    // Bodies that can shade a sphere of `radius` around `id` from any of the
+    // lights; usually none, so most fragments skip the shadow tests entirely
+    fn occluders(&self, id: NodeId, radius: f32, lights: &[Light]) -> Vec<Occluder> {
+        let center = self.nodes[id].world_position;
+
+        self.nodes
+            .iter()
+            .enumerate()
+            .filter(|&(other, node)| other != id && !matches!(node.body, Body::Comet(_)) && node.body.radius() > 0.0)
+            .filter(|(_, node)| {
+                let blocker = node.body.radius();
+                let offset = node.world_position - center;
+                lights.iter().any(|light| {
+                    let to_light = light.position - center;
+                    let distance = to_light.norm();
+                    let along = offset.dot(&to_light) / distance;
+                    if along <= 0.0 || along >= distance {
+                        return false;
+                    }
+                    let across = (offset - to_light * (along / distance)).norm();
+                    // The penumbra widens with distance behind the blocker
+                    let reach = blocker + along * (light.radius + blocker) / (distance - along);
+                    across < radius + reach
+                })
+            })
+            .map(|(_, node)| Occluder {
+                center: node.world_position,
+                radius: node.body.radius(),
+            })
+            .collect()
+    }
+
+    pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &mut Uniforms) {
+        for (id, node) in self.nodes.iter().enumerate() {
+            uniforms.model_matrix = node.world_matrix;
+            uniforms.occluders.clear();
+
+            match &node.body {
+                Body::Barycenter => {}
+                Body::Belt(belt) => {
+                    uniforms.is_star = false;
+                    uniforms.planet_shader = None;
+                    uniforms.terrain = None;
+                    uniforms.ring = None;
+
+                    for (shape, transforms) in belt.shapes.iter().zip(&belt.transforms) {
+                        render_copies(shape, transforms, uniforms, framebuffer);
+                    }
+                }
+                Body::Comet(comet) => {
+                    uniforms.is_star = false;
+                    uniforms.planet_shader = None;
+                    uniforms.terrain = None;
+                    uniforms.ring = None;
+
+                    render_mesh(&comet.mesh, uniforms, framebuffer);
+                }
+                Body::BlackHole(hole) => {
+                    uniforms.is_star = false;
+                    uniforms.planet_shader = None;
+                    uniforms.terrain = None;
+                    uniforms.ring = None;
+                    uniforms.noise.set_seed(hole.seed);
+
+                    uniforms.disk = Some(hole.flow());
+                    render_mesh(&hole.disk, uniforms, framebuffer);
+                    uniforms.disk = None;
+                }
+                Body::Star(star) => {
+                    uniforms.is_star = true;
+                    uniforms.planet_shader = None;
+                    uniforms.ring = None;
+                    uniforms.terrain = None;
+                    uniforms.noise.set_seed(star.seed);
+                    uniforms.star_temperature = star.temperature;
+
+                    render_mesh(&star.mesh, uniforms, framebuffer);
+                }
+                Body::Planet(planet) => {
+                    uniforms.is_star = false;
+                    uniforms.planet_shader = Some(planet.shader_type);
+                    uniforms.palette = planet.palette;
+                    uniforms.terrain = planet.terrain();
+                    uniforms.noise.set_seed(planet.seed);
+                    uniforms.ring = None;
+
+                    let reach = planet.ring.as_ref().map_or(planet.radius, |ring| ring.outer_radius);
+                    uniforms.occluders = self.occluders(id, reach, &uniforms.lights);
+
+                    render_mesh(&planet.mesh, uniforms, framebuffer);
+
+                    // Drawn after the planet so the part in front blends over it,
+                    // with the planet itself casting a shadow across it
+                    if let Some(ring) = &planet.ring {
+                        uniforms.terrain = None;
+                        uniforms.ring = Some(ring.bands());
+                        uniforms.occluders.push(Occluder {
+                            center: planet.position,
+                            radius: planet.radius,
+                        });
+                        render_mesh(&ring.mesh, uniforms, framebuffer);
+                        uniforms.ring = None;
+                    }
+                }
+            }
+        }
+        uniforms.occluders.clear();
+
+        // Glowing tails go last, so solid bodies behind them don't paint over them
+        for node in &self.nodes {
+            if let Body::Comet(comet) = &node.body {
+                // Tail particles live in the frame of the body the comet orbits
+                let origin = node.parent.map(|parent| self.nodes[parent].world_position).unwrap_or_else(Vec3::zeros);
+                comet.render_tails(&origin, framebuffer, uniforms);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_a_body_orbiting_itself() {
+        let mut scene = Scene::new();
+        let a = scene.add("A", Body::Barycenter, None, None).unwrap();
+        assert!(scene.set_parent(a, Some(a)).is_err());
+        assert_eq!(scene.nodes[a].parent, None);
+    }
+
+    #[test]
+    fn rejects_cycles() {
+        let mut scene = Scene::new();
+        let a = scene.add("A", Body::Barycenter, None, None).unwrap();
+        let b = scene.add("B", Body::Barycenter, Some(a), None).unwrap();
+        assert!(scene.set_parent(a, Some(b)).is_err());
+        assert_eq!(scene.nodes[a].parent, None);
+        assert_eq!(scene.nodes[b].parent, Some(a));
+    }
+
+    #[test]
+    fn rejects_unknown_parents() {
+        let mut scene = Scene::new();
+        let a = scene.add("A", Body::Barycenter, None, None).unwrap();
+        assert!(scene.add("B", Body::Barycenter, Some(5), None).is_err());
+        assert_eq!(scene.nodes.len(), 1);
+        assert!(scene.set_parent(a, Some(5)).is_err());
+        assert!(scene.set_parent(5, None).is_err());
+    }
+}
@@ -0,0 +1,779 @@
+use crate::camera::Camera;
+use crate::celestial::orbit::{MAX_ECCENTRICITY, STAR_GM};
+use crate::celestial::{AsteroidBelt, BlackHole, Comet, OrbitalElements, Planet, PlanetPalette, PlanetShader, Ring, Spin, Star, name_seed};
+use crate::color::Color;
+use crate::scene::{Body, Scene};
+use nalgebra_glm::Vec3;
+use serde::{Deserialize, Serialize};
+use std::f64::consts::TAU;
+
+// On-disk description of a star system. Angles are in degrees, times in
+// seconds and lengths in scene units; bodies are listed parents first.
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SystemFile {
+    #[serde(default)]
+    pub camera: CameraEntry,
+    #[serde(default)]
+    pub skybox: SkyboxEntry,
+    #[serde(rename = "body", default)]
+    pub bodies: Vec<BodyEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CameraEntry {
+    pub eye: [f64; 3],
+    pub target: [f64; 3],
+    // Which way is up on screen; straight up the Y axis when missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub up: Option<[f64; 3]>,
+}
+
+impl Default for CameraEntry {
+    fn default() -> Self {
+        CameraEntry {
+            eye: [0.0, 0.0, -10.0],
+            target: [0.0, 0.0, 0.0],
+            up: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SkyboxEntry {
+    pub stars: usize,
+}
+
+impl Default for SkyboxEntry {
+    fn default() -> Self {
+        SkyboxEntry { stars: 1000 }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum BodyKind {
+    Star,
+    Planet,
+    Barycenter,
+    Belt,
+    Comet,
+    #[serde(rename = "black_hole")]
+    BlackHole,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BodyEntry {
+    pub name: String,
+    pub kind: BodyKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub radius: Option<f64>,
+    // Gravitational parameter; planets default to one derived from size and shader
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mass: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shader: Option<PlanetShader>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u32>,
+    // Fixed offset from the parent, added to the orbit if there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<[f64; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub palette: Option<PaletteEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spin: Option<SpinEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orbit: Option<OrbitEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ring: Option<RingEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub belt: Option<BeltEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub comet: Option<CometEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disk: Option<DiskEntry>,
+}
+
+// Colors are "#rrggbb" strings
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PaletteEntry {
+    pub low: String,
+    pub mid: String,
+    pub high: String,
+    pub polar: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SpinEntry {
+    #[serde(default)]
+    pub tilt: f64,
+    // Sidereal period, negative for retrograde rotation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<f64>,
+    // One turn per orbit, so the same face always points at the parent
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub locked: bool,
+    #[serde(default)]
+    pub angle: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrbitEntry {
+    pub semi_major_axis: f64,
+    #[serde(default)]
+    pub eccentricity: f64,
+    #[serde(default)]
+    pub inclination: f64,
+    #[serde(default)]
+    pub ascending_node: f64,
+    #[serde(default)]
+    pub periapsis: f64,
+    #[serde(default)]
+    pub mean_anomaly: f64,
+    // Derived from the parent's mass when left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub period: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RingEntry {
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+    pub color: String,
+}
+
+// Rocks are scattered from `seed`, so the file only stores how many, where and
+// how far along their orbits they are
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BeltEntry {
+    pub count: usize,
+    pub inner_radius: f64,
+    pub outer_radius: f64,
+    #[serde(default = "default_belt_inclination")]
+    pub max_inclination: f64,
+    // Seconds the rocks have already orbited since they were scattered
+    #[serde(default)]
+    pub elapsed: f64,
+}
+
+fn default_belt_inclination() -> f64 {
+    8.0
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CometEntry {
+    // Distance from the star lighting it where the coma and tails switch on; three
+    // perihelion distances when left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_distance: Option<f64>,
+}
+
+// Accretion disk of a black hole; radii default to 3 and 12 Schwarzschild radii
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiskEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inner_radius: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outer_radius: Option<f64>,
+    // At the inner edge
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+}
+
+// A loaded system: the scene plus where the camera starts and how dense the sky is
+pub struct StarSystem {
+    pub scene: Scene,
+    pub camera: Camera,
+    pub skybox_stars: usize,
+}
+
+pub fn load(path: &str) -> Result<StarSystem, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+    let file: SystemFile = toml::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+    build(&file).map_err(|e| format!("{}: {}", path, e))
+}
+
+pub fn save(path: &str, scene: &Scene, camera: &Camera, skybox_stars: usize) -> Result<(), String> {
+    // The file only holds orbits, which the n-body state has left behind
+    if scene.physics.is_some() {
+        return Err("Cannot save while the n-body simulation runs; press N to go back to the orbits first".to_string());
+    }
+    let file = snapshot(scene, camera, skybox_stars);
+    let text = toml::to_string(&file).map_err(|e| format!("Cannot serialize the scene: {}", e))?;
+    std::fs::write(path, text).map_err(|e| format!("Cannot write {}: {}", path, e))
+}
+
+pub fn build(file: &SystemFile) -> Result<StarSystem, String> {
+    let mut scene = Scene::new();
+    let mut unpaced = Vec::new();
+
+    for (index, entry) in file.bodies.iter().enumerate() {
+        let fail = |message: String| format!("body #{} '{}': {}", index + 1, entry.name, message);
+
+        if entry.name.trim().is_empty() {
+            return Err(fail("name cannot be empty".to_string()));
+        }
+        if scene.find(&entry.name).is_some() {
+            return Err(fail("another body already has this name".to_string()));
+        }
+        check_numbers(entry).map_err(fail)?;
+
+        let parent = match &entry.parent {
+            Some(name) => match scene.find(name) {
+                Some(id) => Some(id),
+                None if file.bodies.iter().any(|b| &b.name == name) => {
+                    return Err(fail(format!("parent '{}' has to be listed before its satellites", name)));
+                }
+                None => return Err(fail(format!("parent '{}' does not exist", name))),
+            },
+            None => None,
+        };
+
+        // Orbits around a barycenter depend on everything bound to it, so
+        // unless they give a period they are paced once all bodies are in
+        let barycentric = parent.is_some_and(|p| matches!(scene.nodes[p].body, Body::Barycenter));
+        let pace_later = barycentric && entry.orbit.as_ref().is_some_and(|orbit| orbit.period.is_none());
+        if pace_later && entry.spin.as_ref().is_some_and(|spin| spin.locked) {
+            return Err(fail("spin.locked around a barycenter needs an orbit.period".to_string()));
+        }
+
+        let orbit = match &entry.orbit {
+            Some(orbit) => Some(build_orbit(orbit, (!barycentric).then(|| scene.mass(parent))).map_err(fail)?),
+            None => None,
+        };
+        let spin = match &entry.spin {
+            Some(spin) => Some(build_spin(spin, orbit.as_ref()).map_err(fail)?),
+            None => None,
+        };
+        let central_mass = parent.map_or(0.0, |p| scene.orbit_mass(p, 0.0));
+        let body = build_body(entry, spin, central_mass).map_err(fail)?;
+
+        let id = scene.add(&entry.name, body, parent, orbit).map_err(fail)?;
+        if let Some(position) = entry.position {
+            scene.nodes[id].offset = to_vec3(position);
+        }
+        if pace_later {
+            unpaced.push((index, id));
+        }
+    }
+
+    for (index, id) in unpaced {
+        let node = &scene.nodes[id];
+        let gm = node.parent.map_or(0.0, |parent| scene.orbit_mass(parent, scene.system_mass(id)));
+        if gm <= 0.0 {
+            return Err(format!(
+                "body #{} '{}': orbit.period is required when nothing with mass orbits the barycenter",
+                index + 1,
+                node.name
+            ));
+        }
+        if let Some(orbit) = &mut scene.nodes[id].orbit {
+            *orbit = orbit.around(gm);
+        }
+    }
+
+    if !scene.nodes.iter().any(|node| matches!(node.body, Body::Star(_))) {
+        return Err("the system needs at least one star".to_string());
+    }
+    scene.update(0.0);
+
+    let camera = [Some(file.camera.eye), Some(file.camera.target), file.camera.up];
+    if camera.iter().flatten().flatten().any(|value| !value.is_finite()) {
+        return Err("camera: eye, target and up must be finite numbers".to_string());
+    }
+    if file.camera.eye == file.camera.target {
+        return Err("camera: eye and target cannot be the same point".to_string());
+    }
+
+    let up = file.camera.up.map(to_vec3).unwrap_or(Vec3::new(0.0, 1.0, 0.0));
+    if up.magnitude() == 0.0 {
+        return Err("camera: up cannot be a zero vector".to_string());
+    }
+
+    Ok(StarSystem {
+        scene,
+        camera: Camera::new(to_vec3(file.camera.eye), to_vec3(file.camera.target), up),
+        skybox_stars: file.skybox.stars,
+    })
+}
+
+// NaN fails every comparison, so it would slip past the range checks below
+// and infinities would pass the positive ones
+fn check_numbers(entry: &BodyEntry) -> Result<(), String> {
+    let mut numbers = vec![("radius", entry.radius), ("mass", entry.mass), ("temperature", entry.temperature)];
+    if let Some(position) = entry.position {
+        numbers.extend(position.map(|value| ("position", Some(value))));
+    }
+    if let Some(spin) = &entry.spin {
+        numbers.extend([("spin.tilt", Some(spin.tilt)), ("spin.period", spin.period), ("spin.angle", Some(spin.angle))]);
+    }
+    if let Some(orbit) = &entry.orbit {
+        numbers.extend([
+            ("orbit.semi_major_axis", Some(orbit.semi_major_axis)),
+            ("orbit.eccentricity", Some(orbit.eccentricity)),
+            ("orbit.inclination", Some(orbit.inclination)),
+            ("orbit.ascending_node", Some(orbit.ascending_node)),
+            ("orbit.periapsis", Some(orbit.periapsis)),
+            ("orbit.mean_anomaly", Some(orbit.mean_anomaly)),
+            ("orbit.period", orbit.period),
+        ]);
+    }
+    if let Some(ring) = &entry.ring {
+        numbers.extend([("ring.inner_radius", Some(ring.inner_radius)), ("ring.outer_radius", Some(ring.outer_radius))]);
+    }
+    if let Some(belt) = &entry.belt {
+        numbers.extend([
+            ("belt.inner_radius", Some(belt.inner_radius)),
+            ("belt.outer_radius", Some(belt.outer_radius)),
+            ("belt.max_inclination", Some(belt.max_inclination)),
+            ("belt.elapsed", Some(belt.elapsed)),
+        ]);
+    }
+    if let Some(comet) = &entry.comet {
+        numbers.push(("comet.active_distance", comet.active_distance));
+    }
+    if let Some(disk) = &entry.disk {
+        numbers.extend([
+            ("disk.inner_radius", disk.inner_radius),
+            ("disk.outer_radius", disk.outer_radius),
+            ("disk.temperature", disk.temperature),
+        ]);
+    }
+
+    if let Some((field, _)) = numbers.iter().find(|(_, value)| value.is_some_and(|v| !v.is_finite())) {
+        return Err(format!("{} must be a finite number", field));
+    }
+    if entry.mass.is_some_and(|mass| mass < 0.0) {
+        return Err("mass cannot be negative".to_string());
+    }
+    Ok(())
+}
+
+// Without a parent mass the orbit is left for the caller to pace
+fn build_orbit(entry: &OrbitEntry, parent_mass: Option<f64>) -> Result<OrbitalElements, String> {
+    if entry.semi_major_axis <= 0.0 {
+        return Err("orbit.semi_major_axis must be positive".to_string());
+    }
+    if !(0.0..=MAX_ECCENTRICITY).contains(&entry.eccentricity) {
+        return Err(format!("orbit.eccentricity must be between 0 and {}", MAX_ECCENTRICITY));
+    }
+
+    let orbit = OrbitalElements::new(
+        entry.semi_major_axis,
+        entry.eccentricity,
+        entry.inclination,
+        entry.ascending_node,
+        entry.periapsis,
+        entry.mean_anomaly,
+    );
+
+    match (entry.period, parent_mass) {
+        (Some(period), _) if period <= 0.0 => Err("orbit.period must be positive".to_string()),
+        (Some(period), _) => Ok(OrbitalElements { mean_motion: TAU / period, ..orbit }),
+        (None, None) => Ok(orbit),
+        (None, Some(mass)) if mass > 0.0 => Ok(orbit.around(mass)),
+        (None, Some(_)) => Err("orbit.period is required when the parent has no mass".to_string()),
+    }
+}
+
+fn build_spin(entry: &SpinEntry, orbit: Option<&OrbitalElements>) -> Result<Spin, String> {
+    let mut spin = match (entry.locked, entry.period) {
+        (true, Some(_)) => return Err("spin.period and spin.locked cannot be used together".to_string()),
+        (true, None) => match orbit {
+            Some(orbit) => Spin::locked(entry.tilt as f32, orbit),
+            None => return Err("spin.locked needs an orbit to lock to".to_string()),
+        },
+        (false, period) => Spin::new(entry.tilt as f32, period.unwrap_or(0.0) as f32),
+    };
+    spin.angle = (entry.angle as f32).to_radians();
+    Ok(spin)
+}
+
+fn build_body(entry: &BodyEntry, spin: Option<Spin>, central_mass: f64) -> Result<Body, String> {
+    match entry.kind {
+        BodyKind::Barycenter => {
+            if entry.radius.is_some() || entry.shader.is_some() {
+                return Err("a barycenter has no radius or shader".to_string());
+            }
+            Ok(Body::Barycenter)
+        }
+        BodyKind::Belt => {
+            let belt = entry.belt.as_ref().ok_or("a belt needs a belt table")?;
+            if belt.inner_radius <= 0.0 || belt.outer_radius <= belt.inner_radius {
+                return Err("belt radii must satisfy 0 < inner_radius < outer_radius".to_string());
+            }
+            if central_mass <= 0.0 {
+                return Err("a belt needs a parent with mass to orbit, listed after the stars of a barycenter".to_string());
+            }
+            let mut rocks = AsteroidBelt::new(
+                belt.count,
+                belt.inner_radius,
+                belt.outer_radius,
+                belt.max_inclination,
+                entry.seed.unwrap_or(0),
+                central_mass,
+            );
+            rocks.elapsed = belt.elapsed;
+            Ok(Body::Belt(rocks))
+        }
+        BodyKind::Comet => {
+            let radius = positive_radius(entry.radius)?;
+            let orbit = entry.orbit.as_ref().ok_or("a comet needs an orbit")?;
+            let active_distance = match entry.comet.as_ref().and_then(|comet| comet.active_distance) {
+                Some(distance) if distance <= 0.0 => return Err("comet.active_distance must be positive".to_string()),
+                Some(distance) => distance,
+                None => 3.0 * orbit.semi_major_axis * (1.0 - orbit.eccentricity),
+            };
+            let mut comet = Comet::new(radius, entry.seed.unwrap_or(0), active_distance);
+            if let Some(spin) = spin {
+                comet.spin = spin;
+            }
+            Ok(Body::Comet(comet))
+        }
+        BodyKind::BlackHole => {
+            if entry.radius.is_some() || entry.shader.is_some() {
+                return Err("a black hole's size follows from its mass; it takes no radius or shader".to_string());
+            }
+            let mass = match entry.mass {
+                Some(mass) if mass > 0.0 => mass,
+                Some(_) => return Err("mass must be positive".to_string()),
+                None => return Err("a black hole needs a mass".to_string()),
+            };
+            let mut hole = BlackHole::new(mass);
+            if let Some(disk) = &entry.disk {
+                let inner = disk.inner_radius.unwrap_or(hole.disk_inner as f64);
+                let outer = disk.outer_radius.unwrap_or(hole.disk_outer as f64);
+                if inner <= hole.schwarzschild_radius() as f64 || outer <= inner {
+                    return Err(format!(
+                        "disk radii must satisfy {} (the horizon) < inner_radius < outer_radius",
+                        hole.schwarzschild_radius()
+                    ));
+                }
+                hole.set_disk(inner as f32, outer as f32);
+                if let Some(temperature) = disk.temperature {
+                    if !(1000.0..=40000.0).contains(&temperature) {
+                        return Err("disk.temperature must be between 1000 and 40000 K".to_string());
+                    }
+                    hole.disk_temperature = temperature as f32;
+                }
+            }
+            if let Some(seed) = entry.seed {
+                hole.seed = seed;
+            }
+            if let Some(spin) = spin {
+                hole.spin = spin;
+            }
+            Ok(Body::BlackHole(hole))
+        }
+        BodyKind::Star => {
+            let radius = positive_radius(entry.radius)?;
+            let mut star = Star::new(radius, Vec3::zeros());
+            if let Some(temperature) = entry.temperature {
+                if !(1000.0..=40000.0).contains(&temperature) {
+                    return Err("temperature must be between 1000 and 40000 K".to_string());
+                }
+                star.temperature = temperature as f32;
+            }
+            star.mass = entry.mass.unwrap_or(STAR_GM);
+            if let Some(seed) = entry.seed {
+                star.seed = seed;
+            }
+            if let Some(spin) = spin {
+                star.spin = spin;
+            }
+            Ok(Body::Star(star))
+        }
+        BodyKind::Planet => {
+            let radius = positive_radius(entry.radius)?;
+            let shader = entry.shader.ok_or("a planet needs a shader")?;
+            let seed = entry.seed.unwrap_or_else(|| name_seed(&entry.name));
+            let mut planet = Planet::new(shader, radius, seed);
+            if let Some(mass) = entry.mass {
+                planet.mass = mass;
+            }
+            if let Some(palette) = &entry.palette {
+                planet.palette = PlanetPalette::new(
+                    parse_color(&palette.low, "palette.low")?,
+                    parse_color(&palette.mid, "palette.mid")?,
+                    parse_color(&palette.high, "palette.high")?,
+                    parse_color(&palette.polar, "palette.polar")?,
+                );
+            }
+            if let Some(spin) = spin {
+                planet.spin = spin;
+            }
+            if let Some(ring) = &entry.ring {
+                if ring.inner_radius <= radius as f64 || ring.outer_radius <= ring.inner_radius {
+                    return Err("ring radii must satisfy radius < inner_radius < outer_radius".to_string());
+                }
+                let color = parse_color(&ring.color, "ring.color")?;
+                planet.ring = Some(Ring::new(ring.inner_radius as f32, ring.outer_radius as f32, color));
+            }
+            Ok(Body::Planet(planet))
+        }
+    }
+}
+
+fn positive_radius(radius: Option<f64>) -> Result<f32, String> {
+    match radius {
+        Some(radius) if radius > 0.0 => Ok(radius as f32),
+        Some(_) => Err("radius must be positive".to_string()),
+        None => Err("radius is required".to_string()),
+    }
+}
+
+fn parse_color(text: &str, field: &str) -> Result<Color, String> {
+    text.strip_prefix('#')
+        .filter(|hex| hex.len() == 6)
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .map(Color::from_hex)
+        .ok_or_else(|| format!("{} '{}' is not a #rrggbb color", field, text))
+}
+
+fn format_color(color: Color) -> String {
+    format!("#{:06x}", color.to_hex())
+}
+
+fn spin_entry(spin: &Spin) -> SpinEntry {
+    SpinEntry {
+        tilt: shortest(spin.axial_tilt.to_degrees()),
+        period: Some(shortest(spin.rotation_period)),
+        locked: false,
+        angle: shortest(spin.angle.to_degrees()),
+    }
+}
+
+fn to_vec3([x, y, z]: [f64; 3]) -> Vec3 {
+    Vec3::new(x as f32, y as f32, z as f32)
+}
+
+fn from_vec3(v: &Vec3) -> [f64; 3] {
+    [shortest(v.x), shortest(v.y), shortest(v.z)]
+}
+
+// Widens an f32 through its shortest decimal form, so 0.4 is saved as 0.4
+// rather than 0.4000000059604645
+fn shortest(value: f32) -> f64 {
+    value.to_string().parse().unwrap_or(value as f64)
+}
+
+// Captures the scene as it is now: orbits keep their elements but start from
+// the current mean anomaly, spins from the current angle and belts from the
+// time their rocks have orbited
+pub fn snapshot(scene: &Scene, camera: &Camera, skybox_stars: usize) -> SystemFile {
+    let bodies = scene
+        .nodes
+        .iter()
+        .map(|node| {
+            let mut entry = BodyEntry {
+                name: node.name.clone(),
+                kind: BodyKind::Barycenter,
+                parent: node.parent.map(|parent| scene.nodes[parent].name.clone()),
+                radius: None,
+                mass: None,
+                temperature: None,
+                shader: None,
+                seed: None,
+                position: (node.offset != Vec3::zeros()).then(|| from_vec3(&node.offset)),
+                palette: None,
+                spin: None,
+                orbit: node.orbit.map(|orbit| OrbitEntry {
+                    semi_major_axis: orbit.semi_major_axis,
+                    eccentricity: orbit.eccentricity,
+                    inclination: orbit.inclination.to_degrees(),
+                    ascending_node: orbit.ascending_node.to_degrees(),
+                    periapsis: orbit.periapsis.to_degrees(),
+                    mean_anomaly: orbit.mean_anomaly(scene.time).to_degrees(),
+                    period: Some(orbit.period()),
+                }),
+                ring: None,
+                belt: None,
+                comet: None,
+                disk: None,
+            };
+
+            match &node.body {
+                Body::Barycenter => {}
+                Body::Belt(belt) => {
+                    entry.kind = BodyKind::Belt;
+                    entry.seed = Some(belt.seed);
+                    entry.belt = Some(BeltEntry {
+                        count: belt.asteroids.len(),
+                        inner_radius: belt.inner_radius,
+                        outer_radius: belt.outer_radius,
+                        max_inclination: belt.max_inclination,
+                        elapsed: belt.elapsed + scene.time,
+                    });
+                }
+                Body::Comet(comet) => {
+                    entry.kind = BodyKind::Comet;
+                    entry.radius = Some(shortest(comet.radius));
+                    entry.seed = Some(comet.seed);
+                    entry.spin = Some(spin_entry(&comet.spin));
+                    entry.comet = Some(CometEntry {
+                        active_distance: Some(comet.active_distance),
+                    });
+                }
+                Body::BlackHole(hole) => {
+                    entry.kind = BodyKind::BlackHole;
+                    entry.mass = Some(hole.mass);
+                    entry.seed = Some(hole.seed);
+                    entry.spin = Some(spin_entry(&hole.spin));
+                    entry.disk = Some(DiskEntry {
+                        inner_radius: Some(shortest(hole.disk_inner)),
+                        outer_radius: Some(shortest(hole.disk_outer)),
+                        temperature: Some(shortest(hole.disk_temperature)),
+                    });
+                }
+                Body::Star(star) => {
+                    entry.kind = BodyKind::Star;
+                    entry.radius = Some(shortest(star.radius));
+                    entry.mass = Some(star.mass);
+                    entry.temperature = Some(shortest(star.temperature));
+                    entry.seed = Some(star.seed);
+                    entry.spin = Some(spin_entry(&star.spin));
+                }
+                Body::Planet(planet) => {
+                    entry.kind = BodyKind::Planet;
+                    entry.radius = Some(shortest(planet.radius));
+                    entry.mass = Some(planet.mass);
+                    entry.shader = Some(planet.shader_type);
+                    entry.seed = Some(planet.seed);
+                    entry.palette = Some(PaletteEntry {
+                        low: format_color(planet.palette.low),
+                        mid: format_color(planet.palette.mid),
+                        high: format_color(planet.palette.high),
+                        polar: format_color(planet.palette.polar),
+                    });
+                    entry.spin = Some(spin_entry(&planet.spin));
+                    entry.ring = planet.ring.as_ref().map(|ring| RingEntry {
+                        inner_radius: shortest(ring.inner_radius),
+                        outer_radius: shortest(ring.outer_radius),
+                        color: format_color(ring.color),
+                    });
+                }
+            }
+
+            entry
+        })
+        .collect();
+
+    SystemFile {
+        camera: CameraEntry {
+            eye: from_vec3(&camera.eye),
+            target: from_vec3(&camera.target()),
+            up: Some(from_vec3(&camera.up())),
+        },
+        skybox: SkyboxEntry { stars: skybox_stars },
+        bodies,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUN: &str = "[[body]]\nname = \"Sun\"\nkind = \"star\"\nradius = 1.5\n";
+
+    fn build_text(text: &str) -> Result<StarSystem, String> {
+        build(&toml::from_str(text).map_err(|e| e.to_string())?)
+    }
+
+    fn error(text: &str) -> String {
+        match build_text(text) {
+            Ok(_) => panic!("accepted:\n{}", text),
+            Err(e) => e,
+        }
+    }
+
+    fn scene_path(name: &str) -> String {
+        format!("{}/scenes/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    #[test]
+    fn loads_every_bundled_scene() {
+        let scenes = std::fs::read_dir(scene_path("")).unwrap();
+        for entry in scenes {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|extension| extension == "toml") {
+                load(path.to_str().unwrap()).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn saved_scenes_load_back_where_they_were() {
+        for name in ["default.toml", "binary.toml", "black_hole.toml"] {
+            let mut system = load(&scene_path(name)).unwrap();
+            system.scene.update(37.25);
+
+            let file = snapshot(&system.scene, &system.camera, system.skybox_stars);
+            let text = toml::to_string(&file).unwrap();
+            let reloaded = build_text(&text).unwrap();
+
+            assert_eq!(reloaded.scene.nodes.len(), system.scene.nodes.len());
+            assert!((reloaded.camera.eye - system.camera.eye).magnitude() < 1e-4);
+            for (before, after) in system.scene.nodes.iter().zip(&reloaded.scene.nodes) {
+                assert_eq!(before.name, after.name);
+                let moved = (before.world_position - after.world_position).magnitude();
+                assert!(moved < 1e-3, "{}: {} moved by {}", name, before.name, moved);
+
+                if let (Body::Belt(before), Body::Belt(after)) = (&before.body, &after.body) {
+                    for (a, b) in before.transforms.iter().flatten().zip(after.transforms.iter().flatten()) {
+                        let rock = (a.column(3) - b.column(3)).magnitude();
+                        assert!(rock < 1e-3, "{}: a belt rock moved by {}", name, rock);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_parents() {
+        let text = format!("{}[[body]]\nname = \"Moon\"\nkind = \"planet\"\nparent = \"Nowhere\"\nshader = \"ice\"\nradius = 0.2\n", SUN);
+        assert!(error(&text).contains("parent 'Nowhere' does not exist"));
+    }
+
+    #[test]
+    fn rejects_negative_radii() {
+        let text = SUN.replace("radius = 1.5", "radius = -1.5");
+        assert!(error(&text).contains("radius must be positive"));
+    }
+
+    #[test]
+    fn rejects_open_orbits() {
+        for eccentricity in ["1.0", "1.5"] {
+            let text = format!(
+                "{}[[body]]\nname = \"Rock\"\nkind = \"planet\"\nparent = \"Sun\"\nshader = \"ice\"\nradius = 0.2\norbit = {{ semi_major_axis = 5.0, eccentricity = {} }}\n",
+                SUN, eccentricity
+            );
+            assert!(error(&text).contains("orbit.eccentricity must be between 0 and"));
+        }
+    }
+
+    #[test]
+    fn rejects_duplicate_names() {
+        let text = format!("{}{}", SUN, SUN);
+        assert!(error(&text).contains("another body already has this name"));
+    }
+
+    #[test]
+    fn rejects_numbers_that_are_not_finite() {
+        let text = SUN.replace("radius = 1.5", "radius = nan");
+        assert!(error(&text).contains("radius must be a finite number"));
+    }
+}
@@ -0,0 +1,106 @@
+use crate::color::Color;
+use crate::shaders::noise::ShaderNoise;
+use crate::shaders::planet_shaders::smoothstep;
+use nalgebra_glm::Vec3;
+
+const CLOUD_HEIGHT: f32 = 0.04;
+
+// Rotates a surface direction around the spin axis; higher latitudes drift slower
+fn drift(surface: &Vec3, time: f32, speed: f32) -> Vec3 {
+    let latitude = surface.y.clamp(-1.0, 1.0).asin();
+    let angle = time * speed * (1.0 + 0.4 * (latitude * 3.0).cos());
+    nalgebra_glm::rotate_vec3(surface, angle, &Vec3::new(0.0, 1.0, 0.0))
+}
+
+pub fn cloud_density(noise: &ShaderNoise, surface: &Vec3, time: f32) -> f32 {
+    let p = drift(surface, time, 0.05) * 2.5 + Vec3::new(0.0, 0.0, time * 0.02);
+    let coverage = noise.warped_fbm(&(p + Vec3::new(31.0, 17.0, 5.0)), 0.6);
+    smoothstep(0.0, 0.35, coverage)
+}
+
+// Blends an animated cloud deck over an already lit surface color. The shadow is
+// the cloud density at the point the light ray crossed the cloud deck on its way in.
+pub fn apply_clouds(
+    surface_color: Color,
+    noise: &ShaderNoise,
+    surface: &Vec3,
+    local_light_dir: &Vec3,
+    cloud_color: Color,
+    time: f32,
+) -> Color {
+    let density = cloud_density(noise, surface, time);
+
+    let shadow_point = (surface + local_light_dir * CLOUD_HEIGHT).normalize();
+    let shadow = cloud_density(noise, &shadow_point, time);
+
+    let shaded_surface = surface_color * (1.0 - 0.45 * shadow);
+    shaded_surface.lerp(&cloud_color, density * 0.9)
+}
+
+pub struct Storm {
+    pub center: Vec3,
+    pub radius: f32,
+    pub strength: f32,
+}
+
+impl Storm {
+    // Position and size follow the seed so every gas giant gets its own persistent storm
+    pub fn from_seed(seed: u32) -> Self {
+        let latitude = -0.45 + (seed % 9) as f32 * 0.1;
+        let longitude = (seed % 360) as f32 * std::f32::consts::PI / 180.0;
+
+        Storm {
+            center: Vec3::new(
+                latitude.cos() * longitude.cos(),
+                latitude.sin(),
+                latitude.cos() * longitude.sin(),
+            ),
+            radius: 0.18 + (seed % 5) as f32 * 0.02,
+            strength: 4.0,
+        }
+    }
+
+    // Normalized elliptical distance from the storm's eye, stretched east-west
+    pub fn distance(&self, surface: &Vec3) -> f32 {
+        // The projection below can't tell the two hemispheres apart
+        if surface.dot(&self.center) <= 0.0 {
+            return f32::INFINITY;
+        }
+        let up = Vec3::new(0.0, 1.0, 0.0);
+        let east = up.cross(&self.center).normalize();
+        let north = self.center.cross(&east);
+
+        let offset = surface - self.center;
+        let x = offset.dot(&east) / 1.8;
+        let y = offset.dot(&north);
+        (x * x + y * y).sqrt() / self.radius
+    }
+
+    // Twists the surrounding bands around the eye, strongest near the centre
+    pub fn swirl(&self, surface: &Vec3) -> Vec3 {
+        let d = self.distance(surface) / 1.6;
+        if d >= 1.0 {
+            return *surface;
+        }
+
+        let falloff = (1.0 - d) * (1.0 - d);
+        nalgebra_glm::rotate_vec3(surface, falloff * self.strength, &self.center)
+    }
+
+    // The storm's own clouds turn rigidly around the eye
+    pub fn spin(&self, surface: &Vec3, time: f32) -> Vec3 {
+        nalgebra_glm::rotate_vec3(surface, time * 0.5, &self.center)
+    }
+}
+
+// Zonal jets: neighbouring bands flow in opposite directions
+pub fn band_flow(surface: &Vec3, time: f32) -> Vec3 {
+    let jet = (surface.y * 10.0).sin();
+    nalgebra_glm::rotate_vec3(surface, time * 0.04 * jet, &Vec3::new(0.0, 1.0, 0.0))
+}
+
+// Thin atmosphere glow along the limb, brightest on the day side
+pub fn apply_rim(surface_color: Color, rim_color: &Color, fresnel: f32, n_dot_l: f32) -> Color {
+    let daylight = smoothstep(-0.3, 0.4, n_dot_l);
+    surface_color + *rim_color * (fresnel * daylight * 0.8)
+}
@@ -0,0 +1,50 @@
+use crate::fragment::Fragment;
+use crate::color::Color;
+use crate::uniforms::Uniforms;
+use crate::shaders::planet_shaders::smoothstep;
+use nalgebra_glm::{Vec3, Vec4};
+
+#[derive(Clone, Copy, Debug)]
+pub struct DiskFlow {
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub temperature: f32,
+    pub gm: f32,
+    pub light_speed: f32,
+}
+
+// Color and opacity of an accretion disk fragment. The gas orbits at Keplerian
+// speed, so clumps shear into streaks, and its color is shifted by the Doppler
+// effect and gravitational redshift: the side coming towards the camera is
+// bluer and much brighter than the receding one.
+pub fn disk_shader(fragment: &Fragment, disk: &DiskFlow, uniforms: &Uniforms) -> (Color, f32) {
+    let p = fragment.vertex_position;
+    let radius = (p.x * p.x + p.z * p.z).sqrt().max(1e-4);
+    let t = ((radius - disk.inner_radius) / (disk.outer_radius - disk.inner_radius)).clamp(0.0, 1.0);
+
+    // Follow the gas: inner rings run ahead of outer ones. Angles grow
+    // counterclockwise seen from +Y, the way orbits run.
+    let angular_speed = (disk.gm / radius.powi(3)).sqrt();
+    let angle = (-p.z).atan2(p.x) - angular_speed * uniforms.time;
+    let swirl_point = Vec3::new(angle.cos() * radius, angle.sin() * radius, radius * 0.5) * 2.0;
+    let swirl = uniforms.noise.fbm(&swirl_point) * 0.5 + 0.5;
+
+    // Orbital velocity of the gas, prograde around the disk's +Y, in world space
+    let direction = Vec3::new(p.z, 0.0, -p.x) / radius;
+    let velocity = (uniforms.model_matrix * Vec4::new(direction.x, direction.y, direction.z, 0.0)).xyz();
+    let beta = ((disk.gm / radius).sqrt() / disk.light_speed).min(0.9);
+    let to_camera = (uniforms.camera_position - fragment.world_position).normalize();
+    let lorentz = 1.0 / (1.0 - beta * beta).sqrt();
+    let doppler = 1.0 / (lorentz * (1.0 - beta * velocity.dot(&to_camera)));
+    let horizon = 2.0 * disk.gm / (disk.light_speed * disk.light_speed);
+    let shift = doppler * (1.0 - horizon / radius).max(0.0).sqrt();
+
+    // Thin-disk temperature profile, then seen through the shift
+    let temperature = disk.temperature * (disk.inner_radius / radius).powf(0.75);
+    let brightness = 0.7 * shift.powi(3) * (disk.inner_radius / radius).powf(0.5) * (0.4 + 1.2 * swirl);
+    let color = Color::from_temperature(temperature * shift) * brightness;
+
+    let edges = smoothstep(0.0, 0.03, t) * smoothstep(1.0, 0.6, t);
+    let density = (0.55 + 0.45 * swirl) * edges;
+    (color, density.clamp(0.0, 0.95))
+}
@@ -3,20 +3,21 @@ use crate::color::Color;
 use crate::uniforms::Uniforms;
 use crate::shaders::planet_shaders::planet_shader;
 use crate::shaders::star_shader::sun_shader;
-use nalgebra_glm::Vec3;
+use crate::shaders::lighting::incoming;
 
 pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     if uniforms.is_star {
         sun_shader(fragment, uniforms)
     } else if let Some(shader_type) = uniforms.planet_shader {
-        planet_shader(fragment, shader_type)
+        planet_shader(fragment, shader_type, uniforms)
     } else {
-        let light_dir = Vec3::new(0.0, 0.0, -1.0).normalize();
-
-        let intensity = fragment.normal.dot(&light_dir).max(0.0);
+        let (lights, count) = incoming(&fragment.world_position, uniforms);
         let ambient = 0.3;
-        let final_intensity = (ambient + (1.0 - ambient) * intensity).min(1.0);
 
-        fragment.color * final_intensity
+        lights[..count].iter().fold(Color::BLACK, |total, light| {
+            let intensity = fragment.normal.dot(&light.direction).max(0.0) * light.shadow;
+            let final_intensity = (ambient + (1.0 - ambient) * intensity).min(1.0);
+            total + fragment.color * light.color * (light.strength * final_intensity)
+        })
     }
 }
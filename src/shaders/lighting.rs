@@ -0,0 +1,95 @@
+use crate::color::Color;
+use crate::shaders::planet_shaders::smoothstep;
+use crate::uniforms::{Light, Occluder, Uniforms};
+use nalgebra_glm::Vec3;
+
+// Stars a single fragment is lit by; any beyond these are ignored
+pub const MAX_LIGHTS: usize = 4;
+
+// Light reaching one point from one star
+#[derive(Clone, Copy, Debug)]
+pub struct Incoming {
+    pub direction: Vec3,
+    pub color: Color,
+    // This star's share of all the light arriving here
+    pub strength: f32,
+    // 1 in full light, 0 when eclipsed
+    pub shadow: f32,
+}
+
+impl Incoming {
+    // Light color scaled by share and shadow, for terms with no ambient part
+    pub fn direct(&self) -> Color {
+        self.color * (self.strength * self.shadow)
+    }
+}
+
+// The light at `point`, split between the stars by how much each delivers there
+// (luminosity over distance squared). The shares add up to one, so a lone star
+// lights the scene as brightly as ever, whatever the distance.
+pub fn incoming(point: &Vec3, uniforms: &Uniforms) -> ([Incoming; MAX_LIGHTS], usize) {
+    let lights = &uniforms.lights[..uniforms.lights.len().min(MAX_LIGHTS)];
+    let irradiance = |light: &Light| light.luminosity / (light.position - point).norm_squared().max(1e-6);
+    let total: f32 = lights.iter().map(irradiance).sum();
+
+    let mut incoming = [Incoming {
+        direction: Vec3::y(),
+        color: Color::BLACK,
+        strength: 0.0,
+        shadow: 0.0,
+    }; MAX_LIGHTS];
+
+    for (slot, light) in incoming.iter_mut().zip(lights) {
+        *slot = Incoming {
+            direction: (light.position - point).normalize(),
+            color: light.color,
+            strength: if total > 0.0 { irradiance(light) / total } else { 0.0 },
+            shadow: visibility(point, light, &uniforms.occluders),
+        };
+    }
+
+    (incoming, lights.len())
+}
+
+// Fraction of the light's disc visible from `point`, with a soft penumbra
+pub fn visibility(point: &Vec3, light: &Light, occluders: &[Occluder]) -> f32 {
+    let to_light = light.position - point;
+    let light_distance = to_light.norm();
+    let direction = to_light / light_distance;
+    let light_size = (light.radius / light_distance).min(1.0).asin();
+
+    let mut visible = 1.0;
+    for occluder in occluders {
+        let offset = occluder.center - point;
+        let distance = offset.norm();
+        // Inside the occluder, or it is the light itself or further away than it
+        if distance <= occluder.radius || distance >= light_distance - light.radius * 0.5 {
+            continue;
+        }
+        let along = offset.dot(&direction);
+        if along <= 0.0 {
+            continue;
+        }
+
+        let size = (occluder.radius / distance).min(1.0).asin();
+        let separation = (along / distance).clamp(-1.0, 1.0).acos();
+        visible *= 1.0 - covered(light_size, size, separation);
+    }
+
+    visible
+}
+
+// How much of a disc of angular radius `light` a disc of angular radius
+// `blocker` hides when their centres are `separation` apart
+fn covered(light: f32, blocker: f32, separation: f32) -> f32 {
+    if separation >= light + blocker {
+        return 0.0;
+    }
+    // Total eclipse, or a transit of something smaller than the light
+    let most = (blocker / light.max(1e-6)).powi(2).min(1.0);
+    let inner = (light - blocker).abs();
+    if separation <= inner {
+        return most;
+    }
+    most * smoothstep(light + blocker, inner, separation)
+}
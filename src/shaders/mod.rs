@@ -2,3 +2,9 @@ pub mod vertex_shader;
 pub mod fragment_shader;
 pub mod planet_shaders;
 pub mod star_shader;
+pub mod ring_shader;
+pub mod disk_shader;
+pub mod terrain;
+pub mod noise;
+pub mod atmosphere;
+pub mod lighting;
@@ -0,0 +1,77 @@
+use nalgebra_glm::Vec3;
+use noise::core::worley::ReturnType;
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin, RidgedMulti, Worley};
+
+// Offsets used to decorrelate the three axes of the warp field
+const WARP_OFFSET_X: [f64; 3] = [0.0, 0.0, 0.0];
+const WARP_OFFSET_Y: [f64; 3] = [5.2, 1.3, 2.8];
+const WARP_OFFSET_Z: [f64; 3] = [1.7, 9.2, 4.1];
+
+pub struct ShaderNoise {
+    pub seed: u32,
+    perlin: Perlin,
+    fbm: Fbm<Perlin>,
+    ridged: RidgedMulti<Perlin>,
+    worley: Worley,
+    cells: Worley,
+}
+
+impl ShaderNoise {
+    pub fn new(seed: u32) -> Self {
+        ShaderNoise {
+            seed,
+            perlin: Perlin::new(seed),
+            fbm: Fbm::<Perlin>::new(seed).set_octaves(5),
+            ridged: RidgedMulti::<Perlin>::new(seed.wrapping_add(1)).set_octaves(4),
+            worley: Worley::new(seed).set_return_type(ReturnType::Distance),
+            cells: Worley::new(seed).set_return_type(ReturnType::Value),
+        }
+    }
+
+    pub fn set_seed(&mut self, seed: u32) {
+        if self.seed != seed {
+            *self = ShaderNoise::new(seed);
+        }
+    }
+
+    pub fn perlin(&self, p: &Vec3) -> f32 {
+        self.perlin.get(to_array(p, &[0.0; 3])) as f32
+    }
+
+    // Fractal Brownian motion, roughly in [-1, 1]
+    pub fn fbm(&self, p: &Vec3) -> f32 {
+        self.fbm.get(to_array(p, &[0.0; 3])) as f32
+    }
+
+    // Ridged multifractal, sharp crests near 1 and flat valleys near -1
+    pub fn ridged(&self, p: &Vec3) -> f32 {
+        self.ridged.get(to_array(p, &[0.0; 3])) as f32
+    }
+
+    // Distance to the nearest cell point, 0 at the centre of a cell
+    pub fn worley(&self, p: &Vec3) -> f32 {
+        (self.worley.get(to_array(p, &[0.0; 3])) as f32 * 0.5 + 0.5).max(0.0)
+    }
+
+    // Random value in [0, 1] shared by every point of the same cell
+    pub fn cell_value(&self, p: &Vec3) -> f32 {
+        self.cells.get(to_array(p, &[0.0; 3])) as f32 * 0.5 + 0.5
+    }
+
+    pub fn warp(&self, p: &Vec3, strength: f32) -> Vec3 {
+        let offset = Vec3::new(
+            self.perlin.get(to_array(p, &WARP_OFFSET_X)) as f32,
+            self.perlin.get(to_array(p, &WARP_OFFSET_Y)) as f32,
+            self.perlin.get(to_array(p, &WARP_OFFSET_Z)) as f32,
+        );
+        p + offset * strength
+    }
+
+    pub fn warped_fbm(&self, p: &Vec3, strength: f32) -> f32 {
+        self.fbm(&self.warp(p, strength))
+    }
+}
+
+fn to_array(p: &Vec3, offset: &[f64; 3]) -> [f64; 3] {
+    [p.x as f64 + offset[0], p.y as f64 + offset[1], p.z as f64 + offset[2]]
+}
@@ -1,73 +1,307 @@
 use crate::fragment::Fragment;
 use crate::color::Color;
 use crate::celestial::PlanetShader;
-use nalgebra_glm::Vec3;
+use crate::uniforms::Uniforms;
+use crate::shaders::terrain::{Terrain, TerrainKind};
+use crate::shaders::atmosphere::{apply_clouds, apply_rim, band_flow, Storm};
+use crate::shaders::lighting::{incoming, Incoming, MAX_LIGHTS};
+use nalgebra_glm::{Mat3, Vec3};
 
-fn simple_noise(p: Vec3) -> f32 {
-    let x = (p.x * 50.0).sin() * (p.y * 50.0).cos();
-    let y = (p.y * 50.0).sin() * (p.z * 50.0).cos();
-    let z = (p.z * 50.0).sin() * (p.x * 50.0).cos();
-    ((x + y + z) / 3.0).abs()
+pub fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
 }
 
-pub fn planet_shader(fragment: &Fragment, shader_type: PlanetShader) -> Color {
-    let light_dir = Vec3::new(0.0, 0.0, -1.0).normalize();
-    
-    let intensity = fragment.normal.dot(&light_dir).max(0.0);
-    let ambient = 0.2;
-    let diffuse = (ambient + (1.0 - ambient) * intensity).min(1.0);
-    
-    match shader_type {
-        PlanetShader::Rocky => rocky_shader(fragment, diffuse),
-        PlanetShader::Gaseous => gaseous_shader(fragment, diffuse),
-        PlanetShader::Lava => lava_shader(fragment, diffuse),
+const CITY_LIGHTS: Color = Color::new(255, 200, 110);
+
+// Low ambient so the night side is dark enough for emissive details to read
+const AMBIENT: f32 = 0.08;
+
+struct Lighting {
+    lights: [Incoming; MAX_LIGHTS],
+    count: usize,
+    // Direction to the strongest light in the planet's own frame, for effects sampled on the sphere
+    local_light_dir: Vec3,
+    view_dir: Vec3,
+    normal: Vec3,
+}
+
+impl Lighting {
+    fn lights(&self) -> &[Incoming] {
+        &self.lights[..self.count]
+    }
+
+    // Diffuse response summed over the stars, each tinted by its own color
+    fn shade(&self, color: Color) -> Color {
+        self.lights().iter().fold(Color::BLACK, |total, light| {
+            let intensity = self.normal.dot(&light.direction).max(0.0) * light.shadow;
+            let diffuse = (AMBIENT + (1.0 - AMBIENT) * intensity).min(1.0);
+            total + color * light.color * (light.strength * diffuse)
+        })
+    }
+
+    // Blinn-Phong highlight of every star, in their colors
+    fn specular(&self, shininess: f32) -> Color {
+        self.lights().iter().fold(Color::BLACK, |total, light| {
+            if self.normal.dot(&light.direction) <= 0.0 {
+                return total;
+            }
+            let half_dir = (light.direction + self.view_dir).normalize();
+            total + light.direct() * self.normal.dot(&half_dir).max(0.0).powf(shininess)
+        })
+    }
+
+    // Schlick-style falloff, 0 facing the camera and 1 at grazing angles
+    fn fresnel(&self, power: f32) -> f32 {
+        (1.0 - self.normal.dot(&self.view_dir).max(0.0)).powf(power)
+    }
+
+    // 1 where no star shines, fading across each terminator
+    fn night(&self) -> f32 {
+        let daylight: f32 = self
+            .lights()
+            .iter()
+            .map(|light| light.strength * light.shadow * smoothstep(-0.15, 0.05, self.normal.dot(&light.direction)))
+            .sum();
+        1.0 - daylight.min(1.0)
+    }
+
+    // Atmospheric glow along the limb, lit by each star separately
+    fn rim(&self, color: Color, rim_color: Color) -> Color {
+        let fresnel = self.fresnel(3.0);
+        self.lights().iter().fold(color, |color, light| {
+            apply_rim(color, &(rim_color * light.direct()), fresnel, self.normal.dot(&light.direction))
+        })
+    }
+}
+
+pub fn planet_shader(fragment: &Fragment, shader_type: PlanetShader, uniforms: &Uniforms) -> Color {
+    let (lights, count) = incoming(&fragment.world_position, uniforms);
+    let view_dir = (uniforms.camera_position - fragment.world_position).normalize();
+
+    let strongest = lights[..count]
+        .iter()
+        .max_by(|a, b| (a.strength * a.shadow).total_cmp(&(b.strength * b.shadow)))
+        .map_or(Vec3::y(), |light| light.direction);
+
+    // The model rotation is orthonormal, so its transpose takes world to object space
+    let m = &uniforms.model_matrix;
+    let inverse_rotation = Mat3::new(
+        m[0], m[1], m[2],
+        m[4], m[5], m[6],
+        m[8], m[9], m[10],
+    );
+    let local_light_dir = (inverse_rotation * strongest).normalize();
+
+    let lighting = Lighting {
+        lights,
+        count,
+        local_light_dir,
+        view_dir,
+        normal: fragment.normal,
+    };
+
+    // Patterns are sampled on the undisplaced sphere so they stick to the surface
+    let surface = fragment.vertex_position.normalize();
+
+    let color = match shader_type {
+        PlanetShader::Rocky => rocky_shader(&surface, uniforms, &lighting),
+        PlanetShader::Gaseous => gaseous_shader(&surface, uniforms, &lighting),
+        PlanetShader::Lava => lava_shader(&surface, uniforms, &lighting),
+        PlanetShader::Ice => ice_shader(&surface, uniforms, &lighting),
+        PlanetShader::Desert => desert_shader(&surface, uniforms, &lighting),
+        PlanetShader::Ocean => ocean_shader(&surface, uniforms, &lighting),
+        PlanetShader::Cratered => cratered_shader(&surface, uniforms, &lighting),
+        PlanetShader::Terrestrial => terrestrial_shader(&surface, uniforms, &lighting),
+    };
+
+    let color = match shader_type {
+        PlanetShader::Rocky | PlanetShader::Terrestrial | PlanetShader::Ocean => apply_clouds(
+            color,
+            &uniforms.noise,
+            &surface,
+            &lighting.local_light_dir,
+            lighting.shade(Color::WHITE),
+            uniforms.time,
+        ),
+        _ => color,
+    };
+
+    let rim_color = match shader_type {
+        PlanetShader::Rocky | PlanetShader::Terrestrial | PlanetShader::Ocean => Color::new(120, 180, 255),
+        PlanetShader::Gaseous | PlanetShader::Ice => uniforms.palette.polar,
+        PlanetShader::Desert => Color::new(230, 160, 110),
+        PlanetShader::Lava => Color::new(255, 90, 40),
+        PlanetShader::Cratered => return color,
+    };
+    lighting.rim(color, rim_color)
+}
+
+fn elevation(surface: &Vec3, uniforms: &Uniforms, fallback: TerrainKind) -> f32 {
+    let terrain = uniforms.terrain.unwrap_or(Terrain::new(fallback, 0.0));
+    terrain.elevation(&uniforms.noise, surface)
+}
+
+// Wobbly polar cap boundary; returns 1 on the cap and 0 away from it
+fn polar_cap(surface: &Vec3, uniforms: &Uniforms, latitude: f32) -> f32 {
+    let edge = uniforms.noise.fbm(&(surface * 5.0)) * 0.05;
+    smoothstep(latitude - 0.02, latitude + 0.02, surface.y.abs() + edge)
+}
+
+// Clusters of settlements on habitable lowland, only visible on the night side
+fn city_lights(surface: &Vec3, uniforms: &Uniforms, lighting: &Lighting, elevation: f32) -> Color {
+    let noise = &uniforms.noise;
+
+    let habitable = smoothstep(0.45, 0.1, elevation) * smoothstep(0.75, 0.6, surface.y.abs());
+    let regions = smoothstep(0.0, 0.3, noise.fbm(&(surface * 3.0 + Vec3::new(3.1, 8.7, 2.2))));
+    let towns = smoothstep(0.3, 0.05, noise.worley(&(surface * 25.0)));
+
+    CITY_LIGHTS * (habitable * regions * towns * lighting.night())
+}
+
+fn rocky_shader(surface: &Vec3, uniforms: &Uniforms, lighting: &Lighting) -> Color {
+    let palette = &uniforms.palette;
+    let elevation = elevation(surface, uniforms, TerrainKind::Mountains);
+    let detail = uniforms.noise.fbm(&(surface * 12.0)) * 0.5 + 0.5;
+
+    let deep_ocean = palette.low * 0.5;
+
+    if elevation > 0.0 {
+        let lowland = palette.mid.lerp(&palette.high, smoothstep(0.1, 0.5, elevation));
+        let base_color = lowland * (0.8 + 0.4 * detail);
+        lighting.shade(base_color) + city_lights(surface, uniforms, lighting, elevation)
+    } else {
+        let base_color = deep_ocean.lerp(&palette.low, smoothstep(-0.5, 0.0, elevation));
+        lighting.shade(base_color) + lighting.specular(48.0)
     }
 }
 
-fn rocky_shader(fragment: &Fragment, diffuse: f32) -> Color {
-    let noise = simple_noise(fragment.normal);
-    
-    let ocean_color = Color::CYAN;
-    let land_color = Color::new(0, 200, 100);
-    
-    let base_color = if noise > 0.5 {
-        land_color
+fn gaseous_shader(surface: &Vec3, uniforms: &Uniforms, lighting: &Lighting) -> Color {
+    let palette = &uniforms.palette;
+    let noise = &uniforms.noise;
+    let time = uniforms.time;
+
+    let storm = Storm::from_seed(noise.seed);
+    let flowing = band_flow(&storm.swirl(surface), time);
+
+    let turbulence = noise.warped_fbm(&(flowing * 3.0 + Vec3::new(0.0, 0.0, time * 0.01)), 0.8);
+    let band = (flowing.y * 10.0 + turbulence * 1.5).sin() * 0.5 + 0.5;
+
+    let base_color = palette.low.lerp(&palette.mid, band);
+    let base_color = base_color.lerp(&palette.polar, smoothstep(0.75, 0.95, surface.y.abs()));
+
+    let eye = storm.distance(surface);
+    let base_color = if eye < 1.0 {
+        let storm_clouds = noise.fbm(&(storm.spin(surface, time) * 8.0)) * 0.5 + 0.5;
+        let rings = (eye * 9.0).sin() * 0.15;
+        let storm_color = palette.high * (0.8 + 0.4 * storm_clouds + rings);
+        base_color.lerp(&storm_color, smoothstep(1.0, 0.75, eye))
     } else {
-        ocean_color
+        base_color
     };
-    
-    base_color * (0.3 + 0.7 * diffuse)
+
+    lighting.shade(base_color)
 }
 
-fn gaseous_shader(fragment: &Fragment, diffuse: f32) -> Color {
-    let band = (fragment.normal.y * 10.0).sin() * 0.5 + 0.5;
-    
-    let color1 = Color::MAGENTA;
-    let color2 = Color::PURPLE;
-    
-    let base_color = color1.lerp(&color2, band);
-    
-    base_color * (0.3 + 0.7 * diffuse)
+fn lava_shader(surface: &Vec3, uniforms: &Uniforms, lighting: &Lighting) -> Color {
+    let palette = &uniforms.palette;
+    let ridges = elevation(surface, uniforms, TerrainKind::Ridges);
+    let crust = uniforms.noise.fbm(&(surface * 6.0)) * 0.5 + 0.5;
+
+    let lava_amount = smoothstep(0.4, 0.7, ridges);
+    let bright_amount = smoothstep(0.75, 0.95, ridges);
+
+    let rock = palette.low * (0.6 + 0.8 * crust);
+    let base_color = rock.lerp(&palette.mid, lava_amount).lerp(&palette.high, bright_amount);
+
+    let emission = lava_amount * 0.6;
+    lighting.shade(base_color) + base_color * emission
 }
 
-fn lava_shader(fragment: &Fragment, diffuse: f32) -> Color {
-    let noise = simple_noise(fragment.normal * 2.0);
-    
-    let dark_color = Color::new(20, 20, 20);
-    let lava_color = Color::new(255, 100, 0);
-    let bright_lava = Color::new(255, 0, 128);
-    
-    let lava_amount = if noise > 0.6 { 1.0 } else { 0.0 };
-    let bright_amount = if noise > 0.8 { 1.0 } else { 0.0 };
-    
-    let base_color = if bright_amount > 0.5 {
-        bright_lava
-    } else if lava_amount > 0.5 {
-        lava_color
+fn ice_shader(surface: &Vec3, uniforms: &Uniforms, lighting: &Lighting) -> Color {
+    let palette = &uniforms.palette;
+    let noise = &uniforms.noise;
+
+    let ridges = elevation(surface, uniforms, TerrainKind::Mountains);
+    let crevasse = smoothstep(0.7, 0.9, noise.ridged(&noise.warp(&(surface * 4.0), 0.3)));
+
+    let sheet = palette.mid.lerp(&palette.high, smoothstep(0.0, 0.5, ridges));
+    let base_color = sheet.lerp(&palette.low, crevasse);
+    let base_color = base_color.lerp(&palette.polar, polar_cap(surface, uniforms, 0.7));
+
+    lighting.shade(base_color)
+}
+
+fn desert_shader(surface: &Vec3, uniforms: &Uniforms, lighting: &Lighting) -> Color {
+    let palette = &uniforms.palette;
+    let noise = &uniforms.noise;
+
+    let dunes = elevation(surface, uniforms, TerrainKind::Dunes) * 0.5 + 0.5;
+    let outcrops = smoothstep(0.35, 0.55, noise.fbm(&(surface * 2.5)));
+    let grain = noise.perlin(&(surface * 60.0)) * 0.05;
+
+    let sand = palette.low.lerp(&palette.mid, dunes);
+    let base_color = sand.lerp(&palette.high, outcrops) * (1.0 + grain);
+    let base_color = base_color.lerp(&palette.polar, polar_cap(surface, uniforms, 0.92));
+
+    lighting.shade(base_color)
+}
+
+fn ocean_shader(surface: &Vec3, uniforms: &Uniforms, lighting: &Lighting) -> Color {
+    let palette = &uniforms.palette;
+    let noise = &uniforms.noise;
+
+    let depth = noise.warped_fbm(&(surface * 2.0), 0.5);
+    let island = smoothstep(0.45, 0.5, depth);
+
+    let water = palette.low.lerp(&palette.mid, smoothstep(-0.2, 0.45, depth));
+    let base_color = water.lerp(&palette.high, island);
+    let base_color = base_color.lerp(&palette.polar, polar_cap(surface, uniforms, 0.85));
+
+    let shine = lighting.specular(48.0) * (1.0 - island);
+    lighting.shade(base_color) + shine
+}
+
+fn cratered_shader(surface: &Vec3, uniforms: &Uniforms, lighting: &Lighting) -> Color {
+    let palette = &uniforms.palette;
+    let craters = elevation(surface, uniforms, TerrainKind::Craters);
+    let maria = smoothstep(0.2, 0.5, uniforms.noise.fbm(&(surface * 1.5)));
+
+    let regolith = palette.mid.lerp(&palette.low, maria * 0.6);
+    let base_color = if craters < 0.0 {
+        regolith.lerp(&palette.low, smoothstep(0.0, -0.6, craters))
     } else {
-        dark_color
+        regolith.lerp(&palette.high, smoothstep(0.05, 0.3, craters))
     };
-    
-    let emission = lava_amount * 0.3;
-    base_color * (0.3 + 0.7 * diffuse + emission)
+
+    lighting.shade(base_color)
+}
+
+fn terrestrial_shader(surface: &Vec3, uniforms: &Uniforms, lighting: &Lighting) -> Color {
+    let palette = &uniforms.palette;
+    let noise = &uniforms.noise;
+    let elevation = elevation(surface, uniforms, TerrainKind::Mountains);
+
+    if elevation < 0.0 {
+        let deep = palette.low * 0.5;
+        let water = deep.lerp(&palette.low, smoothstep(-0.5, 0.0, elevation));
+        let sea_ice = polar_cap(surface, uniforms, 0.85);
+        let shine = lighting.specular(48.0) * (1.0 - sea_ice);
+        return lighting.shade(water.lerp(&palette.polar, sea_ice)) + shine;
+    }
+
+    // Temperature falls with latitude and altitude; moisture is an independent noise field
+    let latitude = surface.y.abs();
+    let temperature = 1.0 - latitude * 1.1 - elevation * 0.6 + noise.perlin(&(surface * 3.0)) * 0.1;
+    let moisture = noise.fbm(&(surface * 2.0 + Vec3::new(7.3, 1.9, 4.4))) * 0.5 + 0.5;
+
+    let arid = smoothstep(0.45, 0.3, moisture) * smoothstep(0.3, 0.6, temperature);
+    let mountain = smoothstep(0.35, 0.6, elevation);
+    let snow = smoothstep(0.2, 0.1, temperature);
+
+    let land = palette.mid.lerp(&palette.high, arid.max(mountain));
+    let base_color = land.lerp(&palette.polar, snow);
+
+    let lights = city_lights(surface, uniforms, lighting, elevation) * (1.0 - arid) * (1.0 - snow);
+    lighting.shade(base_color) + lights
 }
@@ -0,0 +1,40 @@
+use crate::fragment::Fragment;
+use crate::color::Color;
+use crate::uniforms::Uniforms;
+use crate::shaders::planet_shaders::smoothstep;
+use crate::shaders::lighting::incoming;
+use nalgebra_glm::Vec3;
+
+// Where the main gap sits, as a fraction of the ring's width
+const GAP_POSITION: f32 = 0.62;
+
+#[derive(Clone, Copy, Debug)]
+pub struct RingBands {
+    pub inner_radius: f32,
+    pub outer_radius: f32,
+    pub color: Color,
+}
+
+// Color and opacity of a ring fragment
+pub fn ring_shader(fragment: &Fragment, bands: &RingBands, uniforms: &Uniforms) -> (Color, f32) {
+    let p = fragment.vertex_position;
+    let radius = (p.x * p.x + p.z * p.z).sqrt();
+    let t = ((radius - bands.inner_radius) / (bands.outer_radius - bands.inner_radius)).clamp(0.0, 1.0);
+
+    // Ringlets are noise sampled along the radius only, so they stay concentric
+    let ringlets = uniforms.noise.fbm(&Vec3::new(t * 40.0, 0.5, 0.5)) * 0.5 + 0.5;
+    let gap = smoothstep(0.015, 0.035, (t - GAP_POSITION).abs());
+    let edges = smoothstep(0.0, 0.05, t) * smoothstep(1.0, 0.9, t);
+    let density = (0.35 + 0.65 * ringlets) * (0.6 + 0.4 * (t * std::f32::consts::PI).sin()) * gap * edges;
+
+    // Both faces catch some light; the planet is among the occluders, so its
+    // shadow falls across the ring behind it, once per star
+    let (lights, count) = incoming(&fragment.world_position, uniforms);
+    let light = lights[..count].iter().fold(Color::BLACK, |total, light| {
+        let lighting = 0.05 + (0.2 + 0.75 * fragment.normal.dot(&light.direction).abs()) * light.shadow;
+        total + light.color * (light.strength * lighting)
+    });
+
+    let color = bands.color * (0.75 + 0.5 * ringlets) * light;
+    (color, density.clamp(0.0, 0.9))
+}
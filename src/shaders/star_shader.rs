@@ -1,38 +1,38 @@
 use crate::fragment::Fragment;
 use crate::color::Color;
 use crate::uniforms::Uniforms;
+use crate::shaders::planet_shaders::smoothstep;
 use nalgebra_glm::Vec3;
-use noise::NoiseFn;
 
-pub fn sun_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    let light_dir = Vec3::new(0.0, 0.0, -1.0).normalize();
-
-    let base_intensity = fragment.normal.dot(&light_dir).max(0.0);
+// Linear limb darkening coefficient, close to the Sun's in visible light
+const LIMB_DARKENING: f32 = 0.6;
 
-    let noise_coords = [
-        (fragment.normal.x * 3.0 + uniforms.time * 0.2) as f64,
-        (fragment.normal.y * 3.0) as f64,
-        (fragment.normal.z * 3.0) as f64,
-    ];
-
-    let noise_value = uniforms.noise_generator.get(noise_coords);
-    let turbulence = (noise_value as f32 * 0.5 + 0.5) * 0.3;
+pub fn sun_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    let noise = &uniforms.noise;
+    let time = uniforms.time;
+    let surface = fragment.vertex_position.normalize();
 
-    let dist_from_center = (1.0 - base_intensity).max(0.0);
+    let view_dir = (uniforms.camera_position - fragment.world_position).normalize();
+    let mu = fragment.normal.dot(&view_dir).max(0.0);
+    let limb = 1.0 - LIMB_DARKENING * (1.0 - mu);
 
-    let core_color = Color::new(255, 200, 0);
-    let mid_color = Color::new(255, 100, 0);
-    let edge_color = Color::new(255, 0, 128);
+    // Granulation: convection cells that slowly churn, hot centres and cool lanes
+    let churn = Vec3::new(time * 0.03, time * 0.02, -time * 0.025);
+    let cells = noise.worley(&noise.warp(&(surface * 14.0 + churn), 0.4));
+    let granulation = (0.5 - cells) * 0.12 + noise.fbm(&(surface * 30.0 + churn)) * 0.04;
 
-    let t = (dist_from_center + turbulence).clamp(0.0, 1.0);
+    // Sunspots: low-frequency field drifting with time, dark umbra inside a penumbra
+    let drift = Vec3::new(0.0, 0.0, time * 0.005);
+    let activity = noise.fbm(&(surface * 2.5 + drift + Vec3::new(11.0, 3.0, 7.0)));
+    let penumbra = smoothstep(0.38, 0.45, activity);
+    let umbra = smoothstep(0.47, 0.52, activity);
 
-    let color = if t < 0.5 {
-        core_color.lerp(&mid_color, t * 2.0)
-    } else {
-        mid_color.lerp(&edge_color, (t - 0.5) * 2.0)
-    };
+    let temperature = uniforms.star_temperature
+        * (1.0 + granulation)
+        * (1.0 - 0.18 * penumbra - 0.2 * umbra);
 
-    let pulse = ((uniforms.time * 1.5).sin() * 0.15 + 1.0).max(0.85);
+    // Overdriven so the disc centre saturates like a real photosphere
+    let brightness = 1.35 * limb * (1.0 + granulation * 2.0) * (1.0 - 0.35 * penumbra - 0.4 * umbra);
 
-    color * pulse
+    Color::from_temperature(temperature) * brightness
 }
@@ -0,0 +1,97 @@
+use crate::shaders::noise::ShaderNoise;
+use nalgebra_glm::Vec3;
+
+#[derive(Clone, Copy, Debug)]
+pub enum TerrainKind {
+    Mountains,
+    Ridges,
+    Craters,
+    Dunes,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Terrain {
+    pub kind: TerrainKind,
+    pub amplitude: f32,
+}
+
+impl Terrain {
+    pub fn new(kind: TerrainKind, amplitude: f32) -> Self {
+        Terrain { kind, amplitude }
+    }
+
+    // Raw noise value behind the height field, shared with the fragment shaders
+    // so surface colors line up with the displaced geometry.
+    pub fn elevation(&self, noise: &ShaderNoise, direction: &Vec3) -> f32 {
+        match self.kind {
+            TerrainKind::Mountains => noise.warped_fbm(&(direction * 1.8), 0.35),
+            TerrainKind::Ridges => noise.ridged(&(direction * 1.5)),
+            TerrainKind::Craters => {
+                craters(noise, &(direction * 3.0)) + 0.5 * craters(noise, &(direction * 8.0))
+            }
+            TerrainKind::Dunes => {
+                let drift = noise.fbm(&(direction * 2.0)) * 3.0;
+                (direction.y * 40.0 + direction.x * 12.0 + drift).sin()
+            }
+        }
+    }
+
+    // Height above the base sphere for a unit direction in object space.
+    pub fn height(&self, noise: &ShaderNoise, direction: &Vec3) -> f32 {
+        let h = self.elevation(noise, direction);
+
+        match self.kind {
+            // Lowlands stay on the sphere so only the peaks break the silhouette
+            TerrainKind::Mountains => (h * 1.6).clamp(0.0, 1.0) * self.amplitude,
+            TerrainKind::Ridges => (h * 0.5 + 0.5).clamp(0.0, 1.0) * self.amplitude,
+            TerrainKind::Craters => h * self.amplitude,
+            TerrainKind::Dunes => (h * 0.5 + 0.5) * self.amplitude,
+        }
+    }
+
+    // Displaces a point on the sphere and rebuilds its normal from two nearby samples.
+    pub fn displace(&self, noise: &ShaderNoise, position: &Vec3, normal: &Vec3) -> (Vec3, Vec3) {
+        let radius = position.magnitude();
+        let n = normal.normalize();
+
+        let reference = if n.y.abs() < 0.99 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let tangent = n.cross(&reference).normalize();
+        let bitangent = n.cross(&tangent);
+
+        let eps = 0.01;
+        let surface = |dir: Vec3| dir * (radius + self.height(noise, &dir));
+
+        let p0 = surface(n);
+        let p1 = surface((n + tangent * eps).normalize());
+        let p2 = surface((n + bitangent * eps).normalize());
+
+        let mut new_normal = (p1 - p0).cross(&(p2 - p0)).normalize();
+        if new_normal.dot(&n) < 0.0 {
+            new_normal = -new_normal;
+        }
+
+        (p0, new_normal)
+    }
+}
+
+// Bowl-shaped impact craters with a raised rim, one per Worley cell.
+// Some cells stay empty so the surface is not evenly pockmarked.
+pub fn craters(noise: &ShaderNoise, p: &Vec3) -> f32 {
+    let cell = noise.cell_value(p);
+    if cell < 0.3 {
+        return 0.0;
+    }
+
+    let crater_radius = 0.2 + 0.3 * cell;
+    let x = noise.worley(p) / crater_radius;
+
+    if x < 1.0 {
+        (x * x - 1.0) * 0.8 + 0.3 * x.powi(6)
+    } else {
+        0.3 * (-(x - 1.0) * 6.0).exp()
+    }
+}
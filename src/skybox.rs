@@ -2,7 +2,8 @@ use crate::color::Color;
 use crate::framebuffer::Framebuffer;
 use crate::uniforms::Uniforms;
 use nalgebra_glm::Vec3;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
 pub struct Star {
     pub direction: Vec3,
@@ -16,7 +17,12 @@ pub struct Skybox {
 
 impl Skybox {
     pub fn new(star_count: usize) -> Self {
-        let mut rng = rand::thread_rng();
+        Self::with_seed(star_count, rand::random())
+    }
+
+    // The same seed always gives the same sky
+    pub fn with_seed(star_count: usize, seed: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
         let mut stars = Vec::new();
 
         for _ in 0..star_count {
@@ -1,6 +1,27 @@
-use nalgebra_glm::{Mat4, Vec3};
-use crate::celestial::PlanetShader;
-use noise::Perlin;
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use crate::color::Color;
+use crate::celestial::{PlanetPalette, PlanetShader};
+use crate::shaders::noise::ShaderNoise;
+use crate::shaders::terrain::Terrain;
+use crate::shaders::ring_shader::RingBands;
+use crate::shaders::disk_shader::DiskFlow;
+
+// A star as seen by the shaders
+#[derive(Clone, Copy, Debug)]
+pub struct Light {
+    pub position: Vec3,
+    pub color: Color,
+    pub radius: f32,
+    // Only meaningful compared with the other lights
+    pub luminosity: f32,
+}
+
+// A sphere that may block some light on its way to the body being drawn
+#[derive(Clone, Copy, Debug)]
+pub struct Occluder {
+    pub center: Vec3,
+    pub radius: f32,
+}
 
 pub struct Uniforms {
     pub model_matrix: Mat4,
@@ -8,9 +29,19 @@ pub struct Uniforms {
     pub projection_matrix: Mat4,
     pub viewport_matrix: Mat4,
     pub time: f32,
+    pub camera_position: Vec3,
+    pub lights: Vec<Light>,
+    pub occluders: Vec<Occluder>,
+    pub star_temperature: f32,
     pub planet_shader: Option<PlanetShader>,
+    pub palette: PlanetPalette,
     pub is_star: bool,
-    pub noise_generator: Perlin,
+    pub noise: ShaderNoise,
+    pub terrain: Option<Terrain>,
+    // Set while drawing a planetary ring, which is blended instead of written opaque
+    pub ring: Option<RingBands>,
+    // Set while drawing an accretion disk, blended like a ring but glowing
+    pub disk: Option<DiskFlow>,
 }
 
 impl Uniforms {
@@ -21,10 +52,36 @@ impl Uniforms {
             projection_matrix: Mat4::identity(),
             viewport_matrix: Mat4::identity(),
             time: 0.0,
+            camera_position: Vec3::new(0.0, 0.0, 0.0),
+            lights: Vec::new(),
+            occluders: Vec::new(),
+            star_temperature: 5800.0,
             planet_shader: None,
+            palette: PlanetShader::Rocky.default_palette(),
             is_star: false,
-            noise_generator: Perlin::new(42),
+            noise: ShaderNoise::new(42),
+            terrain: None,
+            ring: None,
+            disk: None,
+        }
+    }
+
+    // Screen-space x, y and NDC depth of a world point, or None if it is behind the camera
+    pub fn world_to_screen(&self, point: &Vec3) -> Option<Vec3> {
+        let clip = self.projection_matrix * self.view_matrix * Vec4::new(point.x, point.y, point.z, 1.0);
+        if clip.w <= 0.0 {
+            return None;
         }
+
+        let ndc = clip / clip.w;
+        let screen = self.viewport_matrix * ndc;
+        Some(Vec3::new(screen.x, screen.y, ndc.z))
+    }
+
+    // Camera right vector, taken from the first row of the view matrix
+    pub fn camera_right(&self) -> Vec3 {
+        let v = &self.view_matrix;
+        Vec3::new(v[(0, 0)], v[(0, 1)], v[(0, 2)])
     }
 }
 
@@ -40,13 +97,3 @@ pub fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
 pub fn create_projection_matrix(fov: f32, aspect: f32, near: f32, far: f32) -> Mat4 {
     nalgebra_glm::perspective(aspect, fov, near, far)
 }
-
-pub fn create_model_matrix(translation: Vec3, scale: f32, rotation: Vec3) -> Mat4 {
-    let translate = nalgebra_glm::translate(&Mat4::identity(), &translation);
-    let scale_mat = nalgebra_glm::scale(&Mat4::identity(), &Vec3::new(scale, scale, scale));
-    let rotate_x = nalgebra_glm::rotate(&Mat4::identity(), rotation.x, &Vec3::new(1.0, 0.0, 0.0));
-    let rotate_y = nalgebra_glm::rotate(&Mat4::identity(), rotation.y, &Vec3::new(0.0, 1.0, 0.0));
-    let rotate_z = nalgebra_glm::rotate(&Mat4::identity(), rotation.z, &Vec3::new(0.0, 0.0, 1.0));
-    
-    translate * rotate_z * rotate_y * rotate_x * scale_mat
-}
@@ -5,12 +5,11 @@ use nalgebra_glm::{Vec2, Vec3, Vec4};
 pub struct Vertex2D {
     pub x: f32,
     pub y: f32,
-    pub color: Color,
 }
 
 impl Vertex2D {
-    pub fn new(x: f32, y: f32, color: Color) -> Self {
-        Vertex2D { x, y, color }
+    pub fn new(x: f32, y: f32) -> Self {
+        Vertex2D { x, y }
     }
 }
 
@@ -22,6 +21,7 @@ pub struct Vertex {
     pub color: Color,
     pub transformed_position: Vec4,
     pub transformed_normal: Vec3,
+    pub world_position: Vec3,
 }
 
 impl Vertex {
@@ -33,6 +33,7 @@ impl Vertex {
             color,
             transformed_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
             transformed_normal: normal,
+            world_position: position,
         }
     }
 }
@@ -1,58 +1,42 @@
 use crate::camera::Camera;
 use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::particles::{Blend, Particle, ParticleSystem};
+use crate::uniforms::Uniforms;
 use nalgebra_glm::Vec3;
 use rand::Rng;
 
-pub struct WarpParticle {
-    pub position: Vec3,
-    pub velocity: Vec3,
-    pub color: Color,
-    pub lifetime: f32,
-}
-
-impl WarpParticle {
-    pub fn new(camera: &Camera) -> Self {
-        let mut rng = rand::thread_rng();
-
-        let offset_x = rng.gen_range(-10.0..10.0);
-        let offset_y = rng.gen_range(-10.0..10.0);
-        let offset_z = rng.gen_range(5.0..20.0);
+fn warp_particle(camera: &Camera) -> Particle {
+    let mut rng = rand::thread_rng();
 
-        let forward = camera.forward();
-        let right = camera.right();
-        let up = Vec3::new(0.0, 1.0, 0.0);
+    let offset_x = rng.gen_range(-10.0..10.0);
+    let offset_y = rng.gen_range(-10.0..10.0);
+    let offset_z = rng.gen_range(5.0..20.0);
 
-        let position = camera.eye
-            + forward * offset_z
-            + right * offset_x
-            + up * offset_y;
+    let forward = camera.forward();
+    let right = camera.right();
+    let up = camera.up();
 
-        let velocity = -forward * 30.0;
+    let position = camera.eye
+        + forward * offset_z
+        + right * offset_x
+        + up * offset_y;
 
-        let color_choice = rng.gen_range(0..3);
-        let color = match color_choice {
-            0 => Color::new(255, 255, 255),
-            1 => Color::CYAN,
-            _ => Color::MAGENTA,
-        };
+    let velocity = -forward * 30.0;
 
-        WarpParticle {
-            position,
-            velocity,
-            color,
-            lifetime: 2.0,
-        }
-    }
+    let color_choice = rng.gen_range(0..3);
+    let color = match color_choice {
+        0 => Color::new(255, 255, 255),
+        1 => Color::CYAN,
+        _ => Color::MAGENTA,
+    };
 
-    pub fn update(&mut self, delta_time: f32) {
-        self.position += self.velocity * delta_time;
-        self.lifetime -= delta_time;
-    }
+    Particle::new(position, velocity, 2.0, color, 0.06)
 }
 
 pub struct WarpEffect {
     pub active: bool,
-    pub particles: Vec<WarpParticle>,
+    pub particles: ParticleSystem,
     spawn_timer: f32,
 }
 
@@ -60,7 +44,7 @@ impl WarpEffect {
     pub fn new() -> Self {
         WarpEffect {
             active: false,
-            particles: Vec::new(),
+            particles: ParticleSystem::new(Blend::Opaque),
             spawn_timer: 0.0,
         }
     }
@@ -72,6 +56,12 @@ impl WarpEffect {
         }
     }
 
+    pub fn set_active(&mut self, active: bool) {
+        if self.active != active {
+            self.toggle();
+        }
+    }
+
     pub fn update(&mut self, delta_time: f32, camera: &Camera) {
         if !self.active {
             return;
@@ -80,14 +70,15 @@ impl WarpEffect {
         self.spawn_timer -= delta_time;
         if self.spawn_timer <= 0.0 {
             for _ in 0..10 {
-                self.particles.push(WarpParticle::new(camera));
+                self.particles.emit(warp_particle(camera), 0.0);
             }
             self.spawn_timer = 0.03;
         }
 
-        self.particles.retain_mut(|p| {
-            p.update(delta_time);
-            p.lifetime > 0.0
-        });
+        self.particles.update(delta_time);
+    }
+
+    pub fn render(&self, framebuffer: &mut Framebuffer, uniforms: &Uniforms) {
+        self.particles.render(&Vec3::zeros(), framebuffer, uniforms);
     }
 }